@@ -0,0 +1,11 @@
+#!/usr/bin/env python3
+# Keeps the paddle level with the ball. Run with
+#   netpong man "bot:python3 bots/follow.py"
+import json
+import sys
+
+for line in sys.stdin:
+    state = json.loads(line)
+    ball = state["ball"]
+    axis = "x" if state["side"] in ("top", "bottom") else "y"
+    print(json.dumps({"goto": ball[axis]}), flush=True)
@@ -0,0 +1,41 @@
+#!/usr/bin/env python3
+# Talks to `netpong gym` and plays a few episodes with a simple policy.
+# Swap `policy` for a learner; only the standard library is needed.
+import json
+import socket
+
+
+class NetpongEnv:
+    def __init__(self, host="127.0.0.1", port=34522):
+        self.sock = socket.create_connection((host, port))
+        self.file = self.sock.makefile("rw")
+
+    def _ask(self, request):
+        self.file.write(json.dumps(request) + "\n")
+        self.file.flush()
+        reply = json.loads(self.file.readline())
+        if "error" in reply:
+            raise ValueError(reply["error"])
+        return reply["observation"], reply["reward"], reply["done"]
+
+    def reset(self, seed=0):
+        return self._ask({"cmd": "reset", "seed": seed})[0]
+
+    def step(self, action):
+        return self._ask({"cmd": "step", "action": action})
+
+
+def policy(observation):
+    # follow the ball: its y less the paddle's, both scaled to -1..1
+    ball_y, paddle_y = observation[1], observation[4]
+    return max(-1.0, min(1.0, (ball_y - paddle_y) * 10.0))
+
+
+if __name__ == "__main__":
+    env = NetpongEnv()
+    for episode in range(3):
+        observation, total, done = env.reset(seed=episode), 0.0, False
+        while not done:
+            observation, reward, done = env.step(policy(observation))
+            total += reward
+        print("episode", episode, "reward", total)
@@ -2,9 +2,13 @@ import serial
 import time
 ser = serial.Serial("COM8", 115200, timeout=0)
 
+# prints each token as netpong reads it: U/D turns, X<button><S|C|L> or P presses
+pending = b""
 while True:
-    data = ser.read(100)
-    if data:
-        print(data)
+    pending += ser.read(100)
+    *tokens, pending = pending.split(b";")
+    for token in tokens:
+        kind = {b"U": "up", b"D": "down", b"X": "press", b"P": "press"}.get(token[:1], "unknown")
+        print(kind, token.decode(errors="replace"))
     time.sleep(0.01666)
     
\ No newline at end of file
@@ -0,0 +1,377 @@
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+use std::str::FromStr;
+
+use rand::Rng;
+
+use crate::{Position, Velocity};
+use crate::collision::{self, Paddle, Side};
+use crate::input::{Command, Frame, PaddleInput};
+use crate::physics::PhysicsConfig;
+use crate::random;
+
+// bins for the slopes of the returns an adaptive computer has seen
+const SLOPE_BINS: usize = 9;
+// bins for where along their paddle opponents make those returns
+const REGION_BINS: usize = 5;
+// returns seen before it trusts what it has learned about half way
+const TRUST: f32 = 6.0;
+// how far each point moves an adaptive computer between easy and hard
+const ADAPT_STEP: f32 = 0.1;
+// how close to an opponent's paddle the ball turns round to count as a return
+const RETURN_REACH: f32 = 60.0;
+
+// How well the computer plays. Perfect is the old demo mode and can't
+// miss; the rest only see the ball late, guess where it is going and
+// can't move the paddle any faster than a person could. Adaptive starts
+// out normal, learns where its opponent likes to send the ball and which
+// part of the paddle they hit it with, and gets easier or harder to keep
+// the score close.
+#[derive(PartialEq, Clone, Copy, Debug, Default)]
+pub enum Level {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+    Perfect,
+    Adaptive,
+}
+
+struct Skill {
+    // fastest the paddle moves, pixels per second
+    speed: f32,
+    // seconds before a change in the ball's path is noticed
+    reaction: f32,
+    // spread of the predicted intercept, pixels per 100 pixels to go
+    error: f32,
+    // spread of where on the paddle it means to hit, fraction of the length
+    aim: f32,
+}
+
+impl Skill {
+    // `t` of the way from `a` to `b`
+    fn blend(a: &Skill, b: &Skill, t: f32) -> Skill {
+        let mix = |x: f32, y: f32| x + (y - x) * t;
+        Skill{
+            speed: mix(a.speed, b.speed),
+            reaction: mix(a.reaction, b.reaction),
+            error: mix(a.error, b.error),
+            aim: mix(a.aim, b.aim),
+        }
+    }
+}
+
+impl Level {
+    fn skill(self) -> Skill {
+        match self {
+            Level::Easy    => Skill{speed: 260.0, reaction: 0.30, error: 12.0, aim: 0.45},
+            Level::Normal  => Skill{speed: 380.0, reaction: 0.18, error: 6.0,  aim: 0.30},
+            Level::Hard    => Skill{speed: 520.0, reaction: 0.10, error: 2.5,  aim: 0.15},
+            Level::Perfect => Skill{speed: f32::MAX, reaction: 0.0, error: 0.0, aim: 0.0},
+            Level::Adaptive => Skill::blend(&Level::Easy.skill(), &Level::Hard.skill(), 0.5),
+        }
+    }
+}
+
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "easy"    => Ok(Level::Easy),
+            "normal"  => Ok(Level::Normal),
+            "hard"    => Ok(Level::Hard),
+            "perfect" => Ok(Level::Perfect),
+            "adaptive" => Ok(Level::Adaptive),
+            _ => Err(format!("unknown computer level '{}', try easy, normal, hard, perfect or adaptive", s)),
+        }
+    }
+}
+
+// the part of a velocity along a side's wall
+fn along_vel(side: Side, v: Velocity) -> f32 {
+    if side.horizontal() {v.x} else {v.y}
+}
+
+pub struct ComputerInput {
+    skill: Skill,
+    radius: f32,
+    width: f32,
+    // steepest rebound off a paddle, radians
+    max_angle: f32,
+    t: f32,
+    // what it has seen of the ball, oldest first
+    seen: VecDeque<(f32, Position, Velocity)>,
+    // offset from the true intercept for the ball on its way in, picked
+    // once per approach
+    miss: Option<f32>,
+    // how far between easy (0.0) and hard (1.0) an adaptive computer is
+    adapt: Option<f32>,
+    // its own score and the best opponent's when it last looked
+    scores: (i32, i32),
+    // how often opponents have returned the ball at each slope, along the
+    // wall per unit towards it, this match
+    returns: [f32; SLOPE_BINS],
+    // and where on their paddle they made them, end to end
+    regions: [f32; REGION_BINS],
+    // speed the ball was last closing at
+    closing: f32,
+}
+
+impl ComputerInput {
+    pub fn new(level: Level, physics: &PhysicsConfig) -> Self {
+        ComputerInput{
+            skill: level.skill(),
+            radius: physics.ball_radius,
+            width: physics.paddle_width,
+            max_angle: physics.max_angle * PI / 180.0,
+            t: 0.0,
+            seen: VecDeque::new(),
+            miss: None,
+            adapt: if level == Level::Adaptive {Some(0.5)} else {None},
+            scores: (0, 0),
+            returns: [0.0; SLOPE_BINS],
+            regions: [0.0; REGION_BINS],
+            closing: 0.0,
+        }
+    }
+
+    fn max_slope(&self) -> f32 {
+        self.max_angle.tan()
+    }
+
+    // the ball as it was `reaction` seconds ago
+    fn perceive(&mut self, frame: &Frame) -> (Position, Velocity) {
+        self.t += frame.dt;
+        self.seen.push_back((self.t, frame.ball, frame.ball_vel));
+        while self.seen.len() > 1 && self.seen[1].0 <= self.t - self.skill.reaction {
+            self.seen.pop_front();
+        }
+        let (_, ball, vel) = self.seen[0];
+        (ball, vel)
+    }
+
+    // Where along the wall the ball will reach the paddle's face, following
+    // it off the walls either side, and its velocity when it gets there.
+    fn trace(&self, side: Side, ball: Position, vel: Velocity, face: f32) -> Option<(f32, Velocity)> {
+        let closing = -collision::dot(vel, side.normal());
+        let gap = side.depth(ball) - face;
+        if closing <= 0.0 || gap < 0.0 {
+            return None;
+        }
+        let (along, flipped) = self.unfold(side, side.along(ball) + along_vel(side, vel) * gap / closing);
+        let mut vel = vel;
+        if flipped {
+            if side.horizontal() {vel.x = -vel.x} else {vel.y = -vel.y}
+        }
+        Some((along, vel))
+    }
+
+    // Where along the wall the ball, as it is now, will meet this paddle's
+    // face, with no reaction time, guesswork or aiming. None while it is
+    // heading away.
+    pub fn intercept(&self, frame: &Frame) -> Option<f32> {
+        let face = frame.side.depth(frame.paddle) + self.width / 2.0 + self.radius;
+        self.trace(frame.side, frame.ball, frame.ball_vel, face).map(|(along, _)| along)
+    }
+
+    // Where a ball that would have gone straight to `along` really ends up
+    // after bouncing off the walls either side, and whether it is coming
+    // back the other way by then.
+    fn unfold(&self, side: Side, along: f32) -> (f32, bool) {
+        let (lo, width) = (self.radius, side.span() - 2.0 * self.radius);
+        let run = along - lo;
+        let folded = run.rem_euclid(width);
+        if (run / width).floor() as i32 % 2 != 0 {
+            (lo + width - folded, true)
+        } else {
+            (lo + folded, false)
+        }
+    }
+
+    // note the slope of any return an opponent has just made, and where
+    // on their paddle it came off
+    fn learn(&mut self, frame: &Frame, ball: Position, vel: Velocity, closing: f32) {
+        if self.closing > 0.0 || closing <= 0.0 {
+            return;
+        }
+        let paddle = match frame.paddles.iter()
+            .find(|paddle| paddle.side != frame.side && paddle.side.depth(ball) < RETURN_REACH)
+        {
+            Some(paddle) => paddle,
+            None => return,
+        };
+        let slope = along_vel(frame.side, vel) / closing / self.max_slope();
+        let bin = ((slope + 1.0) / 2.0 * SLOPE_BINS as f32).max(0.0) as usize;
+        self.returns[bin.min(SLOPE_BINS - 1)] += 1.0;
+
+        // measured as in Ball::paddle_strike, -1.0 to 1.0 across the paddle
+        let region = (paddle.centre() - paddle.side.along(ball)) / (paddle.length() / 2.0);
+        let bin = ((region + 1.0) / 2.0 * REGION_BINS as f32).max(0.0) as usize;
+        self.regions[bin.min(REGION_BINS - 1)] += 1.0;
+    }
+
+    // The slope, along our wall per unit towards us, a ball arriving at
+    // `vel` leaves an opponent's paddle at when it hits `region` of the
+    // way from its centre to an end, as Ball::paddle_strike works it out.
+    fn rebound(&self, opponent: &Paddle, vel: Velocity, region: f32, side: Side) -> f32 {
+        let length = opponent.length();
+        let d = region * length / 2.0;
+        let offset = if d.abs() <= length / 4.0 {0.0} else {PI * d / (2.0 * length)};
+        let normal = opponent.side.normal();
+        let across = collision::perp(normal);
+        let out = collision::reflect(vel, normal);
+        let theta = collision::dot(across, out).atan2(collision::dot(normal, out));
+        let (sin, cos) = (theta + offset).max(-self.max_angle).min(self.max_angle).sin_cos();
+        let out = Velocity{x: normal.x * cos + across.x * sin, y: normal.y * cos + across.y * sin};
+        along_vel(side, out) / -collision::dot(out, side.normal())
+    }
+
+    // While the ball is on its way to the opponent across the field, where
+    // it will most likely come back to, judging by their returns so far:
+    // half by the slopes they sent it back at, half by playing this ball
+    // off the parts of the paddle they like to use. Pulled towards the
+    // middle until there are enough returns to go on.
+    fn expect_return(&self, frame: &Frame, ball: Position, vel: Velocity) -> Option<f32> {
+        let seen: f32 = self.returns.iter().sum();
+        let side = frame.side;
+        let opponent = frame.paddles.iter().find(|paddle| paddle.side == side.opposite())?;
+        if seen == 0.0 {
+            return None;
+        }
+        let centre = opponent.rect.centre();
+        let their_face = opponent.side.depth(centre) + self.width / 2.0 + self.radius;
+        let (contact, arriving) = self.trace(opponent.side, ball, vel, their_face)?;
+        let our_face = side.depth(frame.paddle) + self.width / 2.0 + self.radius;
+        let distance = side.depth(centre) - self.width / 2.0 - self.radius - our_face;
+
+        let by_slope = self.returns.iter().enumerate().map(|(i, count)| {
+            let slope = ((i as f32 + 0.5) / SLOPE_BINS as f32 * 2.0 - 1.0) * self.max_slope();
+            count * self.unfold(side, contact + slope * distance).0
+        }).sum::<f32>() / seen;
+        let by_region = self.regions.iter().enumerate().map(|(i, count)| {
+            let region = (i as f32 + 0.5) / REGION_BINS as f32 * 2.0 - 1.0;
+            let slope = self.rebound(opponent, arriving, region, side);
+            count * self.unfold(side, contact + slope * distance).0
+        }).sum::<f32>() / self.regions.iter().sum::<f32>();
+        let guess = (by_slope + by_region) / 2.0;
+        let middle = side.span() / 2.0;
+        Some(middle + (guess - middle) * seen / (seen + TRUST))
+    }
+
+    // an adaptive computer that pulls ahead eases off, one that falls
+    // behind tries harder
+    fn adjust(&mut self, frame: &Frame) {
+        let mine = frame.scores[frame.side as usize];
+        let theirs = frame.paddles.iter()
+            .filter(|paddle| paddle.side != frame.side)
+            .map(|paddle| frame.scores[paddle.side as usize])
+            .max()
+            .unwrap_or(0);
+        if (mine, theirs) == self.scores {
+            return;
+        }
+        self.scores = (mine, theirs);
+        if let Some(t) = &mut self.adapt {
+            if mine > theirs {
+                *t -= ADAPT_STEP;
+            } else if mine < theirs {
+                *t += ADAPT_STEP;
+            }
+            *t = t.clamp(0.0, 1.0);
+            self.skill = Skill::blend(&Level::Easy.skill(), &Level::Hard.skill(), *t);
+        }
+    }
+
+    // How far along the wall from the contact point to put the paddle
+    // centre so the return heads for the open end of an opponent's wall,
+    // preferring the one across the field. Works backwards through
+    // Ball::paddle_strike, where hits off the middle half of the paddle
+    // rebound as they came and the rest turn by PI * d / (2 * length).
+    fn aim(&self, frame: &Frame, contact: Position, vel: Velocity) -> f32 {
+        let side = frame.side;
+        let opponent = match frame.paddles.iter()
+            .filter(|paddle| paddle.side != side)
+            .min_by_key(|paddle| paddle.side != side.opposite())
+        {
+            Some(paddle) => paddle,
+            None => return 0.0,
+        };
+        let mut goal = opponent.rect.centre();
+        let span = opponent.side.span();
+        let open = if opponent.centre() > span / 2.0 {0.1} else {0.9};
+        opponent.side.set_along(&mut goal, open * span);
+
+        let normal = side.normal();
+        let across = collision::perp(normal);
+        let heading = |v: Velocity| collision::dot(across, v).atan2(collision::dot(normal, v));
+        let want = heading(Velocity{x: goal.x - contact.x, y: goal.y - contact.y})
+            .max(-self.max_angle).min(self.max_angle);
+        let rebound = heading(collision::reflect(vel, normal));
+
+        let length = frame.height;
+        let d = (want - rebound) * 2.0 * length / PI;
+        if d.abs() <= length / 4.0 {
+            0.0
+        } else {
+            // keep the ball well clear of the paddle's ends
+            d.max(-0.4 * length).min(0.4 * length)
+        }
+    }
+}
+
+impl PaddleInput for ComputerInput {
+    fn poll(&mut self, frame: &Frame) -> Command {
+        let side = frame.side;
+        let (ball, vel) = self.perceive(frame);
+        let along = side.along(frame.paddle);
+        let face = side.depth(frame.paddle) + self.width / 2.0 + self.radius;
+        let closing = -collision::dot(vel, side.normal());
+        if self.adapt.is_some() {
+            self.learn(frame, ball, vel, closing);
+            self.adjust(frame);
+        }
+        self.closing = closing;
+
+        let target = match self.trace(side, ball, vel, face) {
+            None => {
+                // heading away: wait where it is likely to come back, or
+                // failing that in the middle
+                self.miss = None;
+                match self.adapt {
+                    Some(_) => self.expect_return(frame, ball, vel).unwrap_or(side.span() / 2.0),
+                    None => side.span() / 2.0,
+                }
+            },
+            Some((intercept, vel)) => {
+                let skill = &self.skill;
+                let gap = side.depth(ball) - face;
+                let miss = *self.miss.get_or_insert_with(|| random::with(|rng| {
+                    let guess = rng.gen_range(-1.0, 1.0) * skill.error * gap / 100.0;
+                    let aim = rng.gen_range(-1.0, 1.0) * skill.aim * frame.height / 2.0;
+                    guess + aim
+                }));
+                let mut contact = frame.paddle;
+                side.set_along(&mut contact, intercept);
+                intercept + self.aim(frame, contact, vel) + miss
+            },
+        };
+
+        let step = self.skill.speed * frame.dt;
+        Command::Move((target - along).max(-step).min(step))
+    }
+
+    fn serve(&mut self, _frame: &Frame) -> bool {
+        true
+    }
+
+    fn new_match(&mut self) {
+        if self.adapt.is_some() {
+            self.adapt = Some(0.5);
+            self.skill = Level::Adaptive.skill();
+        }
+        self.returns = [0.0; SLOPE_BINS];
+        self.regions = [0.0; REGION_BINS];
+        self.scores = (0, 0);
+    }
+}
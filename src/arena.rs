@@ -0,0 +1,236 @@
+use std::f32::consts::PI;
+use std::fs;
+
+use ggez::{Context, GameResult, graphics};
+use serde::Deserialize;
+
+use crate::{Position, Velocity};
+use crate::collision::{self, Hit, Rect};
+
+const OBSTACLE_COLOUR: graphics::Color = graphics::Color{r: 0.6, g: 0.6, b: 0.6, a: 1.0};
+const PORTAL_IN:  graphics::Color = graphics::Color{r: 0.2, g: 0.6, b: 1.0, a: 1.0};
+const PORTAL_OUT: graphics::Color = graphics::Color{r: 1.0, g: 0.5, b: 0.1, a: 1.0};
+
+// Back and forth movement: the obstacle swings up to (dx, dy) either side
+// of where it was placed, once every `period` seconds. `phase` is how far
+// through the swing it starts, as a fraction of the period.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Motion {
+    pub dx: f32,
+    pub dy: f32,
+    pub period: f32,
+    pub phase: f32,
+}
+
+impl Motion {
+    fn at(&self, x: f32, y: f32, t: f32) -> Position {
+        if self.period <= 0.0 {
+            return Position{x, y};
+        }
+        let s = (2.0 * PI * (t / self.period + self.phase)).sin();
+        Position{x: x + self.dx * s, y: y + self.dy * s}
+    }
+}
+
+// A solid rectangle, given by its centre.
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct Bar {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    #[serde(default)]
+    pub motion: Motion,
+}
+
+impl Bar {
+    fn rect(&self, t: f32) -> Rect {
+        let c = self.motion.at(self.x, self.y, t);
+        Rect::centered(c.x, c.y, self.width, self.height)
+    }
+}
+
+// A solid disc.
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct Bumper {
+    pub x: f32,
+    pub y: f32,
+    pub radius: f32,
+    #[serde(default)]
+    pub motion: Motion,
+}
+
+// Two linked holes: a ball that rolls into either one comes out of the
+// other going the same way.
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct Portal {
+    pub a: [f32; 2],
+    pub b: [f32; 2],
+    pub radius: f32,
+}
+
+// Everything in the middle of the field. An arena file is TOML with any of
+// [[bars]], [[bumpers]] and [[portals]] tables; see the built-in ones below.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Arena {
+    pub bars: Vec<Bar>,
+    pub bumpers: Vec<Bumper>,
+    pub portals: Vec<Portal>,
+    // seconds of play, which drives the moving obstacles
+    #[serde(skip)]
+    t: f32,
+}
+
+const PILLARS: &str = r#"
+[[bars]]
+x = 400.0
+y = 150.0
+width = 12.0
+height = 90.0
+
+[[bars]]
+x = 400.0
+y = 450.0
+width = 12.0
+height = 90.0
+"#;
+
+const GATES: &str = r#"
+[[bars]]
+x = 300.0
+y = 300.0
+width = 12.0
+height = 120.0
+motion = { dy = 200.0, period = 4.0 }
+
+[[bars]]
+x = 500.0
+y = 300.0
+width = 12.0
+height = 120.0
+motion = { dy = 200.0, period = 4.0, phase = 0.5 }
+"#;
+
+const BUMPERS: &str = r#"
+[[bumpers]]
+x = 400.0
+y = 120.0
+radius = 30.0
+
+[[bumpers]]
+x = 400.0
+y = 480.0
+radius = 30.0
+
+[[bumpers]]
+x = 250.0
+y = 300.0
+radius = 20.0
+motion = { dy = 150.0, period = 5.0 }
+
+[[bumpers]]
+x = 550.0
+y = 300.0
+radius = 20.0
+motion = { dy = 150.0, period = 5.0, phase = 0.5 }
+"#;
+
+const PORTALS: &str = r#"
+[[portals]]
+a = [300.0, 100.0]
+b = [500.0, 500.0]
+radius = 24.0
+
+[[portals]]
+a = [300.0, 500.0]
+b = [500.0, 100.0]
+radius = 24.0
+"#;
+
+pub const BUILT_IN: [(&str, &str); 5] = [
+    ("classic", ""),
+    ("pillars", PILLARS),
+    ("gates",   GATES),
+    ("bumpers", BUMPERS),
+    ("portals", PORTALS),
+];
+
+impl Arena {
+    // one of the BUILT_IN arenas by name, otherwise an arena file
+    pub fn load(name: &str) -> Arena {
+        let text = match BUILT_IN.iter().find(|(built_in, _)| *built_in == name) {
+            Some((_, text)) => text.to_string(),
+            None => fs::read_to_string(name).expect("Couldn't read arena file"),
+        };
+        toml::from_str(&text).expect("Couldn't parse arena file")
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.t += dt;
+    }
+
+    // the first obstacle a ball of radius r meets moving by d from p
+    pub fn sweep(&self, p: Position, d: Velocity, r: f32) -> Option<Hit> {
+        let bars = self.bars.iter()
+            .filter_map(|bar| collision::sweep_circle_rect(p, d, r, &bar.rect(self.t)));
+        let bumpers = self.bumpers.iter()
+            .filter_map(|bumper| {
+                let c = bumper.motion.at(bumper.x, bumper.y, self.t);
+                collision::sweep_circle_circle(p, d, r, c, bumper.radius)
+            });
+        bars.chain(bumpers)
+            .filter(|hit| collision::dot(d, hit.normal) < 0.0)
+            .fold(None, |first: Option<Hit>, hit| match first {
+                Some(best) if best.t <= hit.t => first,
+                _ => Some(hit),
+            })
+    }
+
+    // where a ball centred at p comes out, when it is in a portal
+    pub fn portal(&self, p: Position) -> Option<Position> {
+        let inside = |c: [f32; 2], radius: f32| {
+            let (dx, dy) = (p.x - c[0], p.y - c[1]);
+            dx * dx + dy * dy < radius * radius
+        };
+        self.portals.iter().find_map(|portal| {
+            if inside(portal.a, portal.radius) {
+                Some(Position{x: portal.b[0], y: portal.b[1]})
+            } else if inside(portal.b, portal.radius) {
+                Some(Position{x: portal.a[0], y: portal.a[1]})
+            } else {
+                None
+            }
+        })
+    }
+
+    pub fn draw(&self, ctx: &mut Context) -> GameResult {
+        for bar in &self.bars {
+            let rect = bar.rect(self.t);
+            let mesh = graphics::Mesh::new_rectangle(
+                ctx,
+                graphics::DrawMode::fill(),
+                graphics::Rect::new(rect.left, rect.top, rect.right - rect.left, rect.bottom - rect.top),
+                OBSTACLE_COLOUR,
+            )?;
+            graphics::draw(ctx, &mesh, graphics::DrawParam::default())?;
+        }
+        for bumper in &self.bumpers {
+            let c = bumper.motion.at(bumper.x, bumper.y, self.t);
+            let mesh = graphics::Mesh::new_circle(
+                ctx, graphics::DrawMode::fill(), c, bumper.radius, 0.1, OBSTACLE_COLOUR,
+            )?;
+            graphics::draw(ctx, &mesh, graphics::DrawParam::default())?;
+        }
+        for portal in &self.portals {
+            for (c, colour) in [(portal.a, PORTAL_IN), (portal.b, PORTAL_OUT)].iter() {
+                let mesh = graphics::Mesh::new_circle(
+                    ctx, graphics::DrawMode::stroke(3.0), *c, portal.radius, 0.1, *colour,
+                )?;
+                graphics::draw(ctx, &mesh, graphics::DrawParam::default())?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,66 @@
+use ggez::graphics;
+use ggez::{Context, GameResult};
+
+use crate::{SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::ai::Level;
+use crate::collision::Side;
+use crate::physics::PhysicsConfig;
+use crate::player::PlayerType;
+use crate::scoring::{Match, MatchRules};
+use crate::sim::{self, Sim, TICK};
+
+// The game showing itself off while nobody is playing: two computers play
+// each other on the classic field with the sound off, starting over every
+// time one wins. The adaptive one keeps the games close.
+pub struct Attract {
+    sim: Sim,
+    physics: PhysicsConfig,
+    // real seconds not yet simulated
+    lag: f32,
+}
+
+impl Attract {
+    pub fn new(physics: PhysicsConfig) -> Self {
+        Attract{sim: Attract::start(&physics), physics, lag: 0.0}
+    }
+
+    fn start(physics: &PhysicsConfig) -> Sim {
+        let players = vec![
+            sim::player(Side::Left, &PlayerType::Computer(Level::Hard), physics),
+            sim::player(Side::Right, &PlayerType::Computer(Level::Adaptive), physics),
+        ];
+        let rules = MatchRules{points: 5, win_by: 1, games: 1, switch_sides: false};
+        Sim::new(players, Match::new(rules, 2), *physics)
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        // a long stall skips ahead rather than racing to catch up
+        self.lag += dt.min(0.1);
+        while self.lag >= TICK {
+            self.sim.step();
+            self.lag -= TICK;
+        }
+        if self.sim.scoring.winner().is_some() {
+            self.sim = Attract::start(&self.physics);
+        }
+    }
+
+    pub fn draw(&self, ctx: &mut Context) -> GameResult {
+        let mid_line = graphics::Mesh::new_line(
+            ctx,
+            &[[SCREEN_WIDTH/2.0, 0.0], [SCREEN_WIDTH/2.0, SCREEN_HEIGHT]],
+            2.0, graphics::WHITE)?;
+        graphics::draw(ctx, &mid_line, graphics::DrawParam::default())?;
+        self.sim.arena().draw(ctx)?;
+        for player in self.sim.players() {
+            player.draw(ctx)?;
+            player.draw_score(ctx)?;
+        }
+        self.sim.ball().draw(ctx)?;
+
+        let text = graphics::Text::new("DEMO - press any key or turn a knob to play");
+        let r = text.dimensions(ctx);
+        graphics::draw(ctx, &text, graphics::DrawParam::default().dest([(SCREEN_WIDTH - r.w)/2.0, SCREEN_HEIGHT - 40.0]))?;
+        Ok(())
+    }
+}
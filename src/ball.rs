@@ -1,4 +1,4 @@
-use std::f32::consts::PI;
+use std::f32::consts::{PI, SQRT_2};
 
 use ggez::{
     Context,
@@ -7,34 +7,129 @@ use ggez::{
     GameResult
 };
 use ggez::audio::SoundSource;
-use rand::{self, thread_rng, Rng};
+use rand::Rng;
 
 use crate::{Position, Velocity, SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::arena::Arena;
+use crate::collision::{self, Hit, Paddle, Side};
+use crate::rules::Rules;
+use crate::physics::PhysicsConfig;
+use crate::netpong;
+use crate::random;
 
-const RADIUS: f32 = 6.0;
-const BALL_SPEED: f32 = 360.0;
+// most surfaces the ball can touch in a single step
+const MAX_BOUNCES: usize = 8;
+// how far the ball is pushed off a surface after touching it
+const SEPARATION: f32 = 0.01;
+
+// spin (radians per second of heading change) per pixel per second of paddle speed
+const SPIN_TRANSFER: f32 = 0.002;
+// fraction of spin lost per second in flight
+const SPIN_DECAY: f32 = 0.5;
+// sideways kick off a wall, pixels per second per unit of spin
+const SPIN_KICK: f32 = 150.0;
+// rebound angle change, radians per unit of paddle-to-ball speed ratio
+const PADDLE_DRAG: f32 = 0.3;
+
+// pitch step between balls so each one can be told apart by ear
+const PITCH_STEP: f32 = 0.15;
+
+// The game's sound effects, decoded once and shared by every ball.
+pub struct Sounds {
+    ping:  audio::SoundData,
+    pong:  audio::SoundData,
+    table: audio::SoundData,
+}
+
+impl Sounds {
+    pub fn new(ctx: &mut Context) -> Self {
+        Sounds{
+            ping:  audio::SoundData::new(ctx, "/ping.wav").expect("Could load ping sound file"),
+            pong:  audio::SoundData::new(ctx, "/pong.wav").expect("Could load pong sound file"),
+            table: audio::SoundData::new(ctx, "/table.wav").expect("Could load table sound file"),
+        }
+    }
+
+    fn source(ctx: &mut Context, data: &audio::SoundData, pitch: f32) -> audio::Source {
+        let mut source = audio::Source::from_data(ctx, data.clone()).expect("Could not create sound source");
+        source.set_pitch(pitch);
+        source
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Cue {
+    Ping,
+    Pong,
+    Table,
+}
+
+// one ball's sound sources, pitched so each ball can be told apart by ear
+struct Voice {
+    ping:  audio::Source,
+    pong:  audio::Source,
+    table: audio::Source,
+}
 
 pub struct Ball {
+    pub id: u32,
     pub pos: Position,
     pub vel: Velocity,
     pub radius: f32,
-    ping:  audio::Source,
-    pong:  audio::Source,
-    table: audio::Source,
+    pub spin: f32,
+    // speed multiplier and visibility, set by power-ups
+    pub pace: f32,
+    pub hidden: bool,
+    rules: Rules,
+    physics: PhysicsConfig,
+    // none for balls in a headless game
+    voice: Option<Voice>,
+    // sounds from the last update, waiting for play
+    cues: Vec<Cue>,
     consecutive: i32,
+    last_hit: Option<Side>,
+    // still overlapping the portal it last came out of
+    in_portal: bool,
+    // waiting on this paddle to serve, how long for, and whether the
+    // server has pressed serve
+    held: Option<Side>,
+    held_time: f32,
+    release: bool,
     is_game_over: bool,
     delay: f32,
 }
 
 impl Ball {
-    pub fn new(ctx: &mut Context) -> Self {
-        let mut ball = Ball{pos: Position{x:0.0, y:0.0}, 
+    pub fn new(ctx: &mut Context, id: u32, sounds: &Sounds, rules: Rules, physics: PhysicsConfig) -> Self {
+        let pitch = 1.0 + PITCH_STEP * id as f32;
+        let mut ball = Ball::silent(id, rules, physics);
+        ball.voice = Some(Voice{
+            ping:  Sounds::source(ctx, &sounds.ping, pitch),
+            pong:  Sounds::source(ctx, &sounds.pong, pitch),
+            table: Sounds::source(ctx, &sounds.table, pitch),
+        });
+        ball
+    }
+
+    // a ball that makes no sound, for games with no window
+    pub fn silent(id: u32, rules: Rules, physics: PhysicsConfig) -> Self {
+        let mut ball = Ball{id,
+                            pos: Position{x:0.0, y:0.0}, 
                             vel: Velocity{x:0.0, y:0.0},
-                            radius: RADIUS,
-                            ping:  audio::Source::new(ctx, "/ping.wav").expect("Could load pong sound file"),
-                            pong:  audio::Source::new(ctx, "/pong.wav").expect("Could load pong sound file"),
-                            table: audio::Source::new(ctx, "/table.wav").expect("Could load table sound file"),
+                            radius: physics.ball_radius,
+                            spin: 0.0,
+                            pace: 1.0,
+                            hidden: false,
+                            rules,
+                            physics,
+                            voice: None,
+                            cues: vec![],
                             consecutive: 0,
+                            last_hit: None,
+                            in_portal: false,
+                            held: None,
+                            held_time: 0.0,
+                            release: false,
                             is_game_over: false,
                             delay: 0.0,
                         };
@@ -44,25 +139,115 @@ impl Ball {
 
     fn init(&mut self) {
         self.pos.x = SCREEN_WIDTH / 2.0;
+        self.pos.y = SCREEN_HEIGHT / 2.0;
 
-        let mut rng = thread_rng();
-        self.vel.x = match rng.gen_bool(0.5) {
-            true  =>  BALL_SPEED,
-            false => -BALL_SPEED,
+        let speed = self.physics.ball_speed;
+        let (right, down) = random::with(|rng| (rng.gen_bool(0.5), rng.gen_bool(0.5)));
+        self.vel.x = match right {
+            true  =>  speed,
+            false => -speed,
         };
-        self.vel.y = match rng.gen_bool(0.5) {
-            true  =>  BALL_SPEED,
-            false => -BALL_SPEED,
+        self.vel.y = match down {
+            true  =>  speed,
+            false => -speed,
         };
         self.consecutive = 0;
-        self.delay = 0.5;
+        self.last_hit = None;
+        self.spin = 0.0;
+        self.delay = self.physics.serve_delay;
     }
     
+    // wait in front of the paddle on `side` to be served from there
+    pub fn hold(&mut self, side: Side) {
+        self.held = Some(side);
+        self.held_time = 0.0;
+        self.release = false;
+        self.delay = 0.0;
+    }
+
+    pub fn server(&self) -> Option<Side> {
+        self.held
+    }
+
+    // the server pressed serve; the ball goes once the serve delay is up
+    pub fn release(&mut self) {
+        self.release = true;
+    }
+
+    fn launch(&mut self, side: Side) {
+        let normal = side.normal();
+        let across = collision::perp(normal);
+        let max_angle = self.physics.max_angle * PI / 180.0;
+        let (sin, cos) = (random::with(|rng| rng.gen_range(-0.5, 0.5)) * max_angle).sin_cos();
+        // as fast as a classic serve along the diagonal
+        let speed = self.physics.ball_speed * SQRT_2;
+        self.vel.x = (normal.x * cos + across.x * sin) * speed;
+        self.vel.y = (normal.y * cos + across.y * sin) * speed;
+        self.held = None;
+        self.last_hit = Some(side);
+    }
+
+    // Keeps a held ball just in front of its server's paddle and sends it
+    // on its way when the server is ready or has run out of time.
+    fn serve(&mut self, dt: f32, side: Side, paddles: &[Paddle]) {
+        self.held_time += dt;
+        let paddle = match paddles.iter().find(|paddle| paddle.side == side) {
+            Some(paddle) => paddle,
+            None => return self.launch(side),
+        };
+        let rect = paddle.rect;
+        let normal = side.normal();
+        let thickness = if side.horizontal() {rect.bottom - rect.top} else {rect.right - rect.left};
+        let gap = thickness/2.0 + self.radius + SEPARATION;
+        self.pos.x = (rect.left + rect.right)/2.0 + normal.x * gap;
+        self.pos.y = (rect.top + rect.bottom)/2.0 + normal.y * gap;
+        let ready = self.release && self.held_time >= self.physics.serve_delay;
+        if ready || self.held_time >= self.rules.serve_timeout {
+            self.launch(side);
+        }
+    }
+
+    // sets the ball off from `pos` straight away, as from a practice cannon
+    pub fn fire(&mut self, pos: Position, vel: Velocity) {
+        self.pos = pos;
+        self.vel = vel;
+        self.delay = 0.0;
+        self.held = None;
+        self.consecutive = 0;
+        self.last_hit = None;
+        self.spin = 0.0;
+    }
+
     pub fn game_over(&mut self) {
         self.is_game_over = true;
     }
 
+    // consecutive paddle hits since the last serve
+    pub fn rally(&self) -> i32 {
+        self.consecutive
+    }
+
+    // the paddle that touched the ball last since the serve
+    pub fn last_hit(&self) -> Option<Side> {
+        self.last_hit
+    }
+
+    pub fn to_message(&self) -> netpong::Ball {
+        netpong::Ball{x: self.pos.x, y: self.pos.y, vx: self.vel.x, vy: self.vel.y, id: self.id}
+    }
+
+    // take the position and velocity the host sent for this ball
+    pub fn apply(&mut self, message: &netpong::Ball) {
+        self.pos = Position{x: message.x, y: message.y};
+        self.vel = Velocity{x: message.vx, y: message.vy};
+        self.delay = 0.0;
+        self.held = None;
+    }
+
     pub fn draw(&self, ctx: &mut Context) -> GameResult {
+        if self.hidden {
+            return Ok(());
+        }
         let mesh = graphics::Mesh::new_circle(
             ctx,
             graphics::DrawMode::fill(),
@@ -82,69 +267,245 @@ impl Ball {
     }
 
 
-    pub fn update(&mut self, dt: f32, ctx: &mut Context) -> (i32, i32) {
+    // where the ball would go in dt seconds if nothing were in the way
+    pub fn displacement(&self, dt: f32) -> Velocity {
+        let s = dt * self.pace * self.physics.speed_up.powi(self.consecutive/2);
+        Velocity{x: self.vel.x * s, y: self.vel.y * s}
+    }
+
+    // Moves the ball one step. Walls in goals let the ball through; the
+    // side it leaves by is returned and the ball is served again.
+    pub fn update(&mut self, dt: f32, paddles: &[Paddle], goals: &[Side], arena: &Arena) -> Option<Side> {
         if self.delay > 0.0 {
             self.delay -= dt;
-            return (0, 0);
+            return None;
         }
         if self.is_game_over {
             self.pos.y = SCREEN_HEIGHT/2.0;
             self.pos.x = -self.radius;
-            return (0, 0)
+            return None
+        }
+        if let Some(side) = self.held {
+            self.serve(dt, side, paddles);
+            return None;
         }
-        let s = dt * 1.1f32.powi(self.consecutive/2);
-        self.pos.x += self.vel.x * s; 
-        self.pos.y += self.vel.y * s; 
 
-        if self.pos.x <= 0.0 {
-            self.init();
-            return (0, 1);
+        if self.rules.spin {
+            self.curve(dt);
         }
-        if self.pos.x >= SCREEN_WIDTH {
-            self.init();
-            return (1, 0);
+
+        // sweep the ball along its path, bouncing off whatever it meets
+        // first and carrying on with the time that is left
+        let mut remaining = dt;
+        for _ in 0..MAX_BOUNCES {
+            let d = self.displacement(remaining);
+            let walls = Side::ALL.iter()
+                .filter(|side| !goals.contains(side))
+                .filter_map(|&side| collision::sweep_circle_wall(self.pos, d, self.radius, side));
+            let obstacle = arena.sweep(self.pos, d, self.radius);
+            let mut first: Option<(Hit, Option<&Paddle>)> = None;
+            let hits = walls.chain(obstacle).map(|hit| (hit, None))
+                .chain(paddles.iter().filter_map(|paddle| {
+                    collision::sweep_circle_rect(self.pos, d, self.radius, &paddle.rect)
+                        .filter(|hit| collision::dot(self.vel, hit.normal) < 0.0)
+                        .map(|hit| (hit, Some(paddle)))
+                }));
+            for (hit, paddle) in hits {
+                match first {
+                    Some((best, _)) if best.t <= hit.t => (),
+                    _ => first = Some((hit, paddle)),
+                }
+            }
+
+            let (hit, paddle) = match first {
+                Some(first) => first,
+                None => {
+                    self.pos.x += d.x;
+                    self.pos.y += d.y;
+                    break;
+                }
+            };
+            remaining *= 1.0 - hit.t;
+            match paddle {
+                None => {
+                    // bounce off an obstacle or a wall with no goal in it
+                    self.pos = hit.pos;
+                    self.vel = collision::reflect(self.vel, hit.normal);
+                    if self.rules.spin {
+                        self.kick(hit.normal);
+                    }
+                    self.cue(Cue::Table);
+                },
+                Some(paddle) if paddle.is_face(hit.normal) => {
+                    self.paddle_strike(hit.pos.x, hit.pos.y, paddle);
+                },
+                Some(paddle) => {
+                    // glancing off an end or a corner
+                    self.consecutive = (self.consecutive + 1).min(self.physics.max_hits);
+                    self.last_hit = Some(paddle.side);
+                    self.play_paddle(paddle.side);
+                    self.pos = hit.pos;
+                    self.vel = collision::reflect(self.vel, hit.normal);
+                },
+            }
+            self.pos.x += hit.normal.x * SEPARATION;
+            self.pos.y += hit.normal.y * SEPARATION;
+        }
+        for side in Side::ALL.iter().filter(|side| !goals.contains(side)) {
+            let inside = self.radius - side.depth(self.pos);
+            if inside > 0.0 {
+                let normal = side.normal();
+                self.pos.x += normal.x * inside;
+                self.pos.y += normal.y * inside;
+            }
         }
 
-        // floor or ceiling bounce
-        if self.pos.y <= self.radius {
-            self.pos.y = self.radius;
-            self.vel.y = self.vel.y.abs();
-            self.table.play_detached(ctx).unwrap();
-        } else if self.pos.y >= SCREEN_HEIGHT - self.radius {
-            self.pos.y = SCREEN_HEIGHT - self.radius;
-            self.vel.y = -self.vel.y.abs();
-            self.table.play_detached(ctx).unwrap();
+        match arena.portal(self.pos) {
+            Some(exit) if !self.in_portal => {
+                self.pos = exit;
+                self.in_portal = true;
+            },
+            Some(_) => (),
+            None => self.in_portal = false,
         }
-        (0, 0)
+
+        for &side in goals {
+            if side.depth(self.pos) <= 0.0 {
+                self.init();
+                return Some(side);
+            }
+        }
+        None
     }
 
-    pub fn paddle_strike(&mut self, x: f32, y: f32, yp: f32, length: f32, is_left:bool, ctx: &mut Context) {
-        self.consecutive = (self.consecutive + 1).min(44);
-        if is_left {
-            self.ping.play_detached(ctx).unwrap();
+    fn speed(&self) -> f32 {
+        (self.vel.x * self.vel.x + self.vel.y * self.vel.y).sqrt()
+    }
+
+    // spin turns the ball's heading a little every step and wears off
+    fn curve(&mut self, dt: f32) {
+        let (sin, cos) = (self.spin * dt).sin_cos();
+        self.vel = Velocity{
+            x: self.vel.x * cos - self.vel.y * sin,
+            y: self.vel.x * sin + self.vel.y * cos,
+        };
+        self.spin *= (-SPIN_DECAY * dt).exp();
+    }
+
+    // a spinning ball grips the wall and leaves it along a different line
+    fn kick(&mut self, normal: Velocity) {
+        let speed = self.speed();
+        let along = collision::perp(normal);
+        self.vel.x += along.x * self.spin * SPIN_KICK;
+        self.vel.y += along.y * self.spin * SPIN_KICK;
+        let scale = speed / self.speed();
+        self.vel.x *= scale;
+        self.vel.y *= scale;
+        self.spin /= 2.0;
+    }
+
+    fn cue(&mut self, cue: Cue) {
+        if self.voice.is_some() {
+            self.cues.push(cue);
+        }
+    }
+
+    // play the sounds the last update made
+    pub fn play(&mut self, ctx: &mut Context) {
+        let voice = match &mut self.voice {
+            Some(voice) => voice,
+            None => return,
+        };
+        for cue in self.cues.drain(..) {
+            let source = match cue {
+                Cue::Ping  => &mut voice.ping,
+                Cue::Pong  => &mut voice.pong,
+                Cue::Table => &mut voice.table,
+            };
+            source.play_detached(ctx).unwrap();
+        }
+    }
+
+    fn play_paddle(&mut self, side: Side) {
+        if matches!(side, Side::Left | Side::Top) {
+            self.cue(Cue::Ping);
         } else {
-            self.pong.play_detached(ctx).unwrap();
+            self.cue(Cue::Pong);
         }
+    }
+
+    pub fn paddle_strike(&mut self, x: f32, y: f32, paddle: &Paddle) {
+        let (side, length) = (paddle.side, paddle.length());
+        self.consecutive = (self.consecutive + 1).min(self.physics.max_hits);
+        self.last_hit = Some(side);
+        self.play_paddle(side);
         self.pos.x = x;
         self.pos.y = y;
 
-        let dy = yp - y;
-        let offset= if dy.abs() <= length/4.0 {0.0} else {PI * dy / (2.0 * length)};
-        let mut theta = self.vel.y.atan2(-self.vel.x);
-        let mut angle = (((theta + offset) * 180.0/PI) + 360.0) % 360.0;
-        let speed = (self.vel.x * self.vel.x + self.vel.y * self.vel.y).sqrt();
-        if is_left {
-            if  angle > 60.0 && angle <= 180.0 {
-                angle = 60.0;
-            }
-            if angle > 180.0 && angle <= 300.0 {
-                angle = 300.0;
-            }
-        } else {
-            angle = angle.max(120.0).min(240.0);
+        let d = paddle.centre() - side.along(self.pos);
+        let mut offset= if d.abs() <= length/4.0 {0.0} else {PI * d / (2.0 * length)};
+        let speed = self.speed();
+        if self.rules.spin {
+            // the ball leaves in the direction the paddle was moving and
+            // curves back against it
+            let handedness = side.handedness();
+            offset += handedness * PADDLE_DRAG * paddle.vel / speed;
+            self.spin = -handedness * SPIN_TRANSFER * paddle.vel;
         }
-        theta = angle * PI / 180.0;
-        self.vel.x = theta.cos() * speed;
-        self.vel.y = theta.sin() * speed;
+        // rebound heading, measured clockwise from the paddle's normal
+        let normal = side.normal();
+        let across = collision::perp(normal);
+        let out = collision::reflect(self.vel, normal);
+        let max_angle = self.physics.max_angle * PI / 180.0;
+        let theta = collision::dot(across, out).atan2(collision::dot(normal, out));
+        let (sin, cos) = (theta + offset).max(-max_angle).min(max_angle).sin_cos();
+        self.vel.x = (normal.x * cos + across.x * sin) * speed;
+        self.vel.y = (normal.y * cos + across.y * sin) * speed;
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ball() -> Ball {
+        let physics = PhysicsConfig{serve_delay: 0.0, ..PhysicsConfig::default()};
+        Ball::silent(0, Rules::default(), physics)
+    }
+
+    #[test]
+    fn two_walls_in_one_step() {
+        let mut ball = ball();
+        ball.fire(Position{x: 20.0, y: 20.0}, Velocity{x: -600.0, y: -400.0});
+        let goal = ball.update(0.1, &[], &[], &Arena::load("classic"));
+        assert_eq!(goal, None);
+        assert!(ball.vel.x > 0.0 && ball.vel.y > 0.0);
+        assert!(ball.pos.x >= ball.radius && ball.pos.y >= ball.radius);
+    }
+
+    #[test]
+    fn paddle_then_wall_in_one_step() {
+        // off the paddle and on into the top wall before the step is up
+        let paddle = Paddle{rect: collision::Rect::centered(40.0, 30.0, 6.0, 40.0), vel: 0.0, side: Side::Left};
+        let mut ball = ball();
+        ball.fire(Position{x: 120.0, y: 16.0}, Velocity{x: -800.0, y: -20.0});
+        let goal = ball.update(0.2, &[paddle], &[Side::Left, Side::Right], &Arena::load("classic"));
+        assert_eq!(goal, None);
+        assert_eq!(ball.rally(), 1);
+        // heading away from the paddle and back down off the top wall
+        assert!(ball.vel.x > 0.0 && ball.vel.y > 0.0);
+        assert!(ball.pos.x > 43.0 + ball.radius && ball.pos.y >= ball.radius);
+    }
+
+    #[test]
+    fn no_tunnelling_at_top_speed() {
+        let paddle = Paddle{rect: collision::Rect::centered(40.0, 300.0, 6.0, 40.0), vel: 0.0, side: Side::Left};
+        let mut ball = ball();
+        ball.fire(Position{x: 300.0, y: 300.0}, Velocity{x: -ball.physics.ball_speed * SQRT_2, y: 0.0});
+        ball.consecutive = ball.physics.max_hits;
+        let goal = ball.update(0.1, &[paddle], &[Side::Left, Side::Right], &Arena::load("classic"));
+        assert_eq!(goal, None);
+        assert!(ball.vel.x > 0.0);
+        assert!(ball.pos.x > 43.0 + ball.radius);
+    }
+}
@@ -0,0 +1,184 @@
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, Command as Process, Stdio};
+use std::sync::mpsc::{channel, sync_channel, Receiver, SyncSender, TrySendError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::input::{self, Command, Frame, PaddleInput};
+
+// longest to wait each tick for the bot to answer before holding still
+const TIMEOUT: Duration = Duration::from_millis(5);
+// states waiting to be written before newer ones are dropped
+const BACKLOG: usize = 8;
+// shortest time between complaints about lines that aren't replies
+const COMPLAIN_EVERY: Duration = Duration::from_secs(1);
+
+#[derive(Serialize)]
+struct BallState {
+    x: f32,
+    y: f32,
+    vx: f32,
+    vy: f32,
+}
+
+#[derive(Serialize)]
+struct PaddleState {
+    side: &'static str,
+    x: f32,
+    y: f32,
+    length: f32,
+    score: i32,
+}
+
+// One line of JSON to the bot each tick, for example
+//   {"tick":12,"side":"right","dt":0.016,"ball":{"x":400,"y":300,"vx":-360,"vy":120},
+//    "paddles":[{"side":"left","x":40,"y":300,"length":40,"score":0}, ...]}
+#[derive(Serialize)]
+struct State {
+    tick: u64,
+    side: &'static str,
+    dt: f32,
+    ball: BallState,
+    paddles: Vec<PaddleState>,
+}
+
+// and one line back whenever it likes, either
+//   {"move": -4.5}      pixels along the wall this tick
+//   {"goto": 250}       paddle centre along the wall, kept until the next reply
+// with an optional "serve": false to hold on to the ball when serving.
+#[derive(Deserialize)]
+struct Reply {
+    #[serde(rename = "move")]
+    step: Option<f32>,
+    goto: Option<f32>,
+    serve: Option<bool>,
+}
+
+// A paddle driven by another program. It is started with the given
+// command line, reads game state on stdin and writes commands on stdout,
+// so bots can be written in anything. Only the newest reply counts; a
+// bot that falls silent just leaves its paddle where it is. States are written from a thread of their
+// own; a bot that reads too slowly misses some rather than stalling the game.
+pub struct BotInput {
+    child: Child,
+    states: Option<SyncSender<String>>,
+    replies: Receiver<Reply>,
+    tick: u64,
+    serve: bool,
+    goto: Option<f32>,
+}
+
+impl BotInput {
+    pub fn new(command: &str) -> Self {
+        let mut words = command.split_whitespace();
+        let program = words.next().expect("Empty bot command");
+        let mut child = Process::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("Couldn't start bot");
+        let stdout = child.stdout.take().expect("Bot has no stdout");
+        let mut stdin = child.stdin.take().expect("Bot has no stdin");
+
+        let (states, lines) = sync_channel::<String>(BACKLOG);
+        thread::spawn(move || {
+            for line in lines {
+                if writeln!(stdin, "{}", line).and_then(|_| stdin.flush()).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let (send, replies) = channel();
+        thread::spawn(move || {
+            let mut complained: Option<Instant> = None;
+            let mut unsaid = 0;
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                match serde_json::from_str::<Reply>(&line) {
+                    Ok(reply) => if send.send(reply).is_err() {
+                        break;
+                    },
+                    Err(_) if complained.is_some_and(|at| at.elapsed() < COMPLAIN_EVERY) => unsaid += 1,
+                    Err(err) => {
+                        if unsaid > 0 {
+                            eprintln!("bot: {} more bad lines", unsaid);
+                            unsaid = 0;
+                        }
+                        eprintln!("bot: {}: {}", err, line);
+                        complained = Some(Instant::now());
+                    },
+                }
+            }
+        });
+
+        BotInput{child, states: Some(states), replies, tick: 0, serve: true, goto: None}
+    }
+
+    fn tell(&mut self, frame: &Frame) {
+        let state = State{
+            tick: self.tick,
+            side: frame.side.name(),
+            dt: frame.dt,
+            ball: BallState{x: frame.ball.x, y: frame.ball.y, vx: frame.ball_vel.x, vy: frame.ball_vel.y},
+            paddles: frame.paddles.iter().map(|paddle| {
+                let centre = paddle.rect.centre();
+                PaddleState{
+                    side: paddle.side.name(),
+                    x: centre.x,
+                    y: centre.y,
+                    length: paddle.length(),
+                    score: frame.scores[paddle.side as usize],
+                }
+            }).collect(),
+        };
+        self.tick += 1;
+        let line = serde_json::to_string(&state).expect("Couldn't write bot state");
+        let sent = match &self.states {
+            Some(states) => states.try_send(line),
+            None => return,
+        };
+        if let Err(TrySendError::Disconnected(_)) = sent {
+            eprintln!("bot: stopped listening");
+            self.states = None;
+        }
+    }
+
+    // the newest reply, waiting up to TIMEOUT if there isn't one yet
+    fn listen(&mut self) -> Option<Reply> {
+        self.replies.try_iter().last().or_else(|| self.replies.recv_timeout(TIMEOUT).ok())
+    }
+}
+
+impl PaddleInput for BotInput {
+    fn poll(&mut self, frame: &Frame) -> Command {
+        self.tell(frame);
+        let mut step = None;
+        if let Some(reply) = self.listen() {
+            if let Some(serve) = reply.serve {
+                self.serve = serve;
+            }
+            self.goto = reply.goto;
+            step = reply.step;
+        }
+        let step = match (self.goto, step) {
+            (Some(along), _) => along - frame.side.along(frame.paddle),
+            (None, Some(step)) => step,
+            (None, None) => return Command::Hold,
+        };
+        input::bot_move(step, frame.dt)
+    }
+
+    fn serve(&mut self, _frame: &Frame) -> bool {
+        self.serve
+    }
+}
+
+impl Drop for BotInput {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
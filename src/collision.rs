@@ -0,0 +1,401 @@
+use crate::{Position, Velocity, SCREEN_WIDTH, SCREEN_HEIGHT};
+
+// The four walls of the field. Each can hold a player's goal or just
+// bounce the ball back.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+impl Side {
+    // also the slot number sent over the network
+    pub const ALL: [Side; 4] = [Side::Left, Side::Right, Side::Top, Side::Bottom];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Side::Left   => "left",
+            Side::Right  => "right",
+            Side::Top    => "top",
+            Side::Bottom => "bottom",
+        }
+    }
+
+    pub fn opposite(self) -> Side {
+        match self {
+            Side::Left   => Side::Right,
+            Side::Right  => Side::Left,
+            Side::Top    => Side::Bottom,
+            Side::Bottom => Side::Top,
+        }
+    }
+
+    // top and bottom paddles slide along x, left and right along y
+    pub fn horizontal(self) -> bool {
+        matches!(self, Side::Top | Side::Bottom)
+    }
+
+    // unit normal pointing off the wall into the field
+    pub fn normal(self) -> Velocity {
+        match self {
+            Side::Left   => Velocity{x:  1.0, y:  0.0},
+            Side::Right  => Velocity{x: -1.0, y:  0.0},
+            Side::Top    => Velocity{x:  0.0, y:  1.0},
+            Side::Bottom => Velocity{x:  0.0, y: -1.0},
+        }
+    }
+
+    // length of the wall, which is how far a paddle on it can travel
+    pub fn span(self) -> f32 {
+        if self.horizontal() {SCREEN_WIDTH} else {SCREEN_HEIGHT}
+    }
+
+    // coordinate along the wall
+    pub fn along(self, p: Position) -> f32 {
+        if self.horizontal() {p.x} else {p.y}
+    }
+
+    pub fn set_along(self, p: &mut Position, value: f32) {
+        if self.horizontal() {p.x = value} else {p.y = value}
+    }
+
+    // distance from the wall into the field
+    pub fn depth(self, p: Position) -> f32 {
+        match self {
+            Side::Left   => p.x,
+            Side::Right  => SCREEN_WIDTH - p.x,
+            Side::Top    => p.y,
+            Side::Bottom => SCREEN_HEIGHT - p.y,
+        }
+    }
+
+    // +1.0 when the normal turned clockwise (see perp) points the way
+    // positive paddle speed goes, -1.0 when it points the other way
+    pub fn handedness(self) -> f32 {
+        let n = self.normal();
+        if self.horizontal() {-n.y} else {n.x}
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    pub left:   f32,
+    pub top:    f32,
+    pub right:  f32,
+    pub bottom: f32,
+}
+
+impl Rect {
+    pub fn centered(x: f32, y: f32, width: f32, height: f32) -> Rect {
+        Rect{
+            left:   x - width/2.0,
+            top:    y - height/2.0,
+            right:  x + width/2.0,
+            bottom: y + height/2.0,
+        }
+    }
+
+    pub fn centre(&self) -> Position {
+        Position{x: (self.left + self.right) / 2.0, y: (self.top + self.bottom) / 2.0}
+    }
+
+    fn inflate(&self, r: f32) -> Rect {
+        Rect{left: self.left - r, top: self.top - r, right: self.right + r, bottom: self.bottom + r}
+    }
+}
+
+// What the ball needs to know about a paddle to bounce off it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Paddle {
+    pub rect: Rect,
+    // speed along its wall in pixels per second, positive is down or right
+    pub vel: f32,
+    pub side: Side,
+}
+
+impl Paddle {
+    // centre along the wall
+    pub fn centre(&self) -> f32 {
+        self.side.along(self.rect.centre())
+    }
+
+    pub fn length(&self) -> f32 {
+        if self.side.horizontal() {
+            self.rect.right - self.rect.left
+        } else {
+            self.rect.bottom - self.rect.top
+        }
+    }
+
+    // true when the normal points out of the paddle's face into the field
+    pub fn is_face(&self, normal: Velocity) -> bool {
+        dot(normal, self.side.normal()) > 0.5
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hit {
+    // fraction of the sweep at first contact, 0.0..=1.0
+    pub t: f32,
+    // ball centre at contact
+    pub pos: Position,
+    // unit surface normal pointing back at the ball
+    pub normal: Velocity,
+}
+
+fn point(p: Position, d: Velocity, t: f32) -> Position {
+    Position{x: p.x + d.x * t, y: p.y + d.y * t}
+}
+
+pub fn dot(a: Velocity, b: Velocity) -> f32 {
+    a.x * b.x + a.y * b.y
+}
+
+// n turned a quarter clockwise on screen
+pub fn perp(n: Velocity) -> Velocity {
+    Velocity{x: -n.y, y: n.x}
+}
+
+pub fn reflect(v: Velocity, n: Velocity) -> Velocity {
+    let k = 2.0 * dot(v, n);
+    Velocity{x: v.x - k * n.x, y: v.y - k * n.y}
+}
+
+// earliest t in 0..=1 where p + d*t is r away from c
+fn sweep_circle_point(p: Position, d: Velocity, r: f32, c: Position) -> Option<f32> {
+    let m = Velocity{x: p.x - c.x, y: p.y - c.y};
+    let a = dot(d, d);
+    let b = dot(m, d);
+    let k = dot(m, m) - r * r;
+    if a == 0.0 || b >= 0.0 {
+        // not moving, or moving away from the corner
+        return None;
+    }
+    let disc = b * b - a * k;
+    if disc < 0.0 {
+        return None;
+    }
+    let t = (-b - disc.sqrt()) / a;
+    if (0.0..=1.0).contains(&t) {Some(t)} else {None}
+}
+
+// A ball already overlapping the rectangle (a paddle moved onto it) is
+// pushed out through the side it is least buried in.
+fn overlap(p: Position, r: f32, rect: &Rect) -> Option<Hit> {
+    let nx = p.x.max(rect.left).min(rect.right);
+    let ny = p.y.max(rect.top).min(rect.bottom);
+    let (dx, dy) = (p.x - nx, p.y - ny);
+    if dx * dx + dy * dy >= r * r {
+        return None;
+    }
+    let depths = [
+        (p.x - rect.left + r,   Velocity{x: -1.0, y:  0.0}, Position{x: rect.left - r,   y: p.y}),
+        (rect.right + r - p.x,  Velocity{x:  1.0, y:  0.0}, Position{x: rect.right + r,  y: p.y}),
+        (p.y - rect.top + r,    Velocity{x:  0.0, y: -1.0}, Position{x: p.x, y: rect.top - r}),
+        (rect.bottom + r - p.y, Velocity{x:  0.0, y:  1.0}, Position{x: p.x, y: rect.bottom + r}),
+    ];
+    let (_, normal, pos) = depths.iter()
+        .fold(depths[0], |best, &side| if side.0 < best.0 {side} else {best});
+    Some(Hit{t: 0.0, pos, normal})
+}
+
+// Swept circle against an axis-aligned rectangle. This is a ray cast
+// against the rectangle grown by the radius with rounded corners, so the
+// paddle's ends and corners are as solid as its face.
+pub fn sweep_circle_rect(p: Position, d: Velocity, r: f32, rect: &Rect) -> Option<Hit> {
+    if let Some(hit) = overlap(p, r, rect) {
+        return Some(hit);
+    }
+
+    let outer = rect.inflate(r);
+    let mut t_enter = 0.0f32;
+    let mut t_exit  = 1.0f32;
+    let mut normal  = Velocity{x: 0.0, y: 0.0};
+
+    let slabs = [
+        (p.x, d.x, outer.left, outer.right,  Velocity{x: -1.0, y: 0.0}, Velocity{x: 1.0, y: 0.0}),
+        (p.y, d.y, outer.top,  outer.bottom, Velocity{x: 0.0, y: -1.0}, Velocity{x: 0.0, y: 1.0}),
+    ];
+    for &(start, delta, lo, hi, lo_normal, hi_normal) in slabs.iter() {
+        if delta == 0.0 {
+            if start < lo || start > hi {
+                return None;
+            }
+            continue;
+        }
+        let (t0, t1, n) = if delta > 0.0 {
+            ((lo - start) / delta, (hi - start) / delta, lo_normal)
+        } else {
+            ((hi - start) / delta, (lo - start) / delta, hi_normal)
+        };
+        if t0 >= t_enter {
+            t_enter = t0;
+            normal = n;
+        }
+        t_exit = t_exit.min(t1);
+        if t_enter > t_exit {
+            return None;
+        }
+    }
+
+    let q = point(p, d, t_enter);
+    let in_x = q.x >= rect.left && q.x <= rect.right;
+    let in_y = q.y >= rect.top  && q.y <= rect.bottom;
+    if in_x || in_y {
+        return Some(Hit{t: t_enter, pos: q, normal});
+    }
+
+    // entered the grown box in a corner square, so test the rounded corner
+    let corner = Position{
+        x: if q.x < rect.left {rect.left} else {rect.right},
+        y: if q.y < rect.top  {rect.top}  else {rect.bottom},
+    };
+    let t = sweep_circle_point(p, d, r, corner)?;
+    let c = point(p, d, t);
+    let normal = Velocity{x: (c.x - corner.x) / r, y: (c.y - corner.y) / r};
+    Some(Hit{t, pos: c, normal})
+}
+
+// Swept circle against a round obstacle. A ball already touching it is
+// pushed straight out.
+pub fn sweep_circle_circle(p: Position, d: Velocity, r: f32, c: Position, radius: f32) -> Option<Hit> {
+    let reach = r + radius;
+    let m = Velocity{x: p.x - c.x, y: p.y - c.y};
+    let distance = dot(m, m).sqrt();
+    if distance < reach {
+        let normal = if distance > 0.0 {
+            Velocity{x: m.x / distance, y: m.y / distance}
+        } else {
+            Velocity{x: 0.0, y: -1.0}
+        };
+        let pos = Position{x: c.x + normal.x * reach, y: c.y + normal.y * reach};
+        return Some(Hit{t: 0.0, pos, normal});
+    }
+    let t = sweep_circle_point(p, d, reach, c)?;
+    let q = point(p, d, t);
+    let normal = Velocity{x: (q.x - c.x) / reach, y: (q.y - c.y) / reach};
+    Some(Hit{t, pos: q, normal})
+}
+
+// Ball against one of the field's walls.
+pub fn sweep_circle_wall(p: Position, d: Velocity, r: f32, side: Side) -> Option<Hit> {
+    let normal = side.normal();
+    let closing = dot(d, normal);
+    if closing >= 0.0 {
+        return None;
+    }
+    let t = ((r - side.depth(p)) / closing).max(0.0);
+    if t > 1.0 {
+        return None;
+    }
+    Some(Hit{t, pos: point(p, d, t), normal})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::PhysicsConfig;
+
+    const R: f32 = 6.0;
+
+    // a left paddle, 6 wide and 40 long, centred on (40, 300)
+    fn paddle() -> Rect {
+        Rect::centered(40.0, 300.0, 6.0, 40.0)
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-3
+    }
+
+    #[test]
+    fn fast_ball_across_paddle_end() {
+        // straight up through the bottom end, covering the whole paddle in one step
+        let hit = sweep_circle_rect(Position{x: 40.0, y: 400.0}, Velocity{x: 0.0, y: -200.0}, R, &paddle())
+            .expect("went through the end");
+        assert!(close(hit.t, (400.0 - 326.0) / 200.0));
+        assert!(close(hit.pos.y, 326.0));
+        assert_eq!(hit.normal, Velocity{x: 0.0, y: 1.0});
+    }
+
+    #[test]
+    fn corner_hit() {
+        let corner = Position{x: 43.0, y: 320.0};
+        let hit = sweep_circle_rect(Position{x: 80.0, y: 340.0}, Velocity{x: -80.0, y: -40.0}, R, &paddle())
+            .expect("missed the corner");
+        assert!(hit.t > 0.0 && hit.t < 1.0);
+        // touching the corner, pushed away from it down and to the right
+        let (dx, dy) = (hit.pos.x - corner.x, hit.pos.y - corner.y);
+        assert!(close((dx * dx + dy * dy).sqrt(), R));
+        assert!(hit.normal.x > 0.0 && hit.normal.y > 0.0);
+        assert!(close(dot(hit.normal, hit.normal), 1.0));
+    }
+
+    #[test]
+    fn ball_just_clear_of_corner() {
+        // the grown box is square but its corners are round
+        let hit = sweep_circle_rect(Position{x: 58.0, y: 315.0}, Velocity{x: -40.0, y: 40.0}, R, &paddle());
+        assert_eq!(hit, None);
+    }
+
+    #[test]
+    fn paddle_moved_onto_ball() {
+        let hit = sweep_circle_rect(Position{x: 44.0, y: 300.0}, Velocity{x: -5.0, y: 0.0}, R, &paddle())
+            .expect("overlap not resolved");
+        assert_eq!(hit.t, 0.0);
+        assert_eq!(hit.normal, Velocity{x: 1.0, y: 0.0});
+        assert!(close(hit.pos.x, 43.0 + R));
+    }
+
+    #[test]
+    fn paddle_before_wall_in_one_step() {
+        let (p, d) = (Position{x: 100.0, y: 300.0}, Velocity{x: -200.0, y: 0.0});
+        let paddle = sweep_circle_rect(p, d, R, &paddle()).expect("went through the paddle");
+        let wall = sweep_circle_wall(p, d, R, Side::Left).expect("went through the wall");
+        assert!(close(paddle.t, (100.0 - 49.0) / 200.0));
+        assert!(close(wall.t, (100.0 - 6.0) / 200.0));
+        assert!(paddle.t < wall.t);
+    }
+
+    #[test]
+    fn long_step_at_top_speed() {
+        let physics = PhysicsConfig::default();
+        let speed = physics.ball_speed * std::f32::consts::SQRT_2 * physics.speed_up.powi(physics.max_hits / 2);
+        let d = Velocity{x: -speed * 0.1, y: 0.0};
+        assert!(-d.x > 300.0 - 40.0, "step should reach past the paddle");
+        let hit = sweep_circle_rect(Position{x: 300.0, y: 300.0}, d, R, &paddle()).expect("tunnelled");
+        assert!(close(hit.pos.x, 49.0));
+        assert_eq!(hit.normal, Velocity{x: 1.0, y: 0.0});
+    }
+
+    #[test]
+    fn moving_away_from_paddle() {
+        assert_eq!(sweep_circle_rect(Position{x: 60.0, y: 300.0}, Velocity{x: 100.0, y: 0.0}, R, &paddle()), None);
+        assert_eq!(sweep_circle_rect(Position{x: 100.0, y: 200.0}, Velocity{x: -100.0, y: 0.0}, R, &paddle()), None);
+    }
+
+    #[test]
+    fn wall_hit() {
+        let hit = sweep_circle_wall(Position{x: 300.0, y: 50.0}, Velocity{x: 40.0, y: -88.0}, R, Side::Top)
+            .expect("went through the top");
+        assert!(close(hit.t, 0.5));
+        assert!(close(hit.pos.y, R));
+        assert!(close(hit.pos.x, 320.0));
+        assert_eq!(hit.normal, Velocity{x: 0.0, y: 1.0});
+    }
+
+    #[test]
+    fn wall_out_of_reach_or_behind() {
+        assert_eq!(sweep_circle_wall(Position{x: 100.0, y: 300.0}, Velocity{x: -50.0, y: 0.0}, R, Side::Left), None);
+        assert_eq!(sweep_circle_wall(Position{x: 100.0, y: 300.0}, Velocity{x: 50.0, y: 0.0}, R, Side::Left), None);
+    }
+
+    #[test]
+    fn ball_already_in_wall() {
+        let hit = sweep_circle_wall(Position{x: SCREEN_WIDTH - 2.0, y: 300.0}, Velocity{x: 10.0, y: 0.0}, R, Side::Right)
+            .expect("left inside the wall");
+        assert_eq!(hit.t, 0.0);
+        assert_eq!(hit.normal, Velocity{x: -1.0, y: 0.0});
+    }
+}
@@ -1,12 +1,24 @@
 
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::str;
+use std::fs;
 use serialport::SerialPort;
 
 const BAUD_RATE: u32 = 115_200; 
 
 pub struct FlexControl {
     flex: Box<dyn SerialPort>,
+    last: Option<SerialRead>,
+    // the knob has been pushed since the last take_press
+    pressed: bool,
+}
+
+// timing of a single read, for latency diagnostics
+#[derive(Clone, Copy, Debug)]
+pub struct SerialRead {
+    pub started: Instant,
+    pub took: Duration,
+    pub counts: i32,
 }
 
 impl FlexControl {
@@ -16,26 +28,82 @@ impl FlexControl {
             .open()
             .expect("Couldn't open flex control");
 
-        FlexControl{flex}
+        FlexControl{flex, last: None, pressed: false}
     }
 
+    // Counts turned since the last read. The device sends ';'-terminated
+    // tokens:
+    //   U[n]      turned up n counts (1 if left out)
+    //   D[n]      turned down n counts
+    //   X<b><t>   button b tapped (S), double tapped (C) or held (L), which
+    //             is how the stock firmware reports the knob push and the
+    //             three buttons; any of them counts as a press
+    //   P         a push, from firmware with only the knob switch
+    // flexcontrol/flexcontrol.py prints what a given device sends.
     pub fn read(&mut self) -> i32 {
+        let started = Instant::now();
         let mut buf: Vec<u8> = vec![0; 128];
         let movement = match self.flex.read(buf.as_mut_slice()) {
             Ok(t) => {
                 let recvd = str::from_utf8(&buf[..t]).unwrap();
+                let pressed = &mut self.pressed;
                 recvd.split(';')
                     .filter(|x| x.len() > 0)
                     .map(|x| {
                         match x.split_at(1) {
                             ("D", count) => -count.parse::<i32>().unwrap_or(1),
                             ("U", count) =>  count.parse::<i32>().unwrap_or(1),
+                            ("X", _) | ("P", _) => {*pressed = true; 0},
                             (_,_) => 0,
                         }
                     }).sum()
             },
             _ => 0,
         };
+        self.last = Some(SerialRead{started, took: started.elapsed(), counts: movement});
         movement
     }
+
+    pub fn take_last_read(&mut self) -> Option<SerialRead> {
+        self.last.take()
+    }
+
+    pub fn take_press(&mut self) -> bool {
+        std::mem::replace(&mut self.pressed, false)
+    }
+}
+
+// Knob travel in encoder counts, captured by turning the knob from the stop
+// that should mean the top of the screen to the one that means the bottom.
+// Counts start from wherever the knob happens to be at launch, so only the
+// span is kept; where the stops are has to be found again each time.
+#[derive(Clone, Copy, Debug)]
+pub struct Calibration {
+    pub span: i32,
+}
+
+impl Calibration {
+    fn path(port: &str) -> String {
+        let name: String = port.chars()
+            .map(|c| if c.is_ascii_alphanumeric() {c} else {'_'})
+            .collect();
+        format!("flexcontrol-{}.cal", name)
+    }
+
+    pub fn load(port: &str) -> Option<Calibration> {
+        let text = fs::read_to_string(Calibration::path(port)).ok()?;
+        let values: Vec<i32> = text.split_whitespace().map_while(|x| x.parse().ok()).collect();
+        // older files hold the min and max counts of the launch they came from
+        let span = match values[..] {
+            [span] => span,
+            [min, max] => max - min,
+            _ => return None,
+        };
+        if span > 0 {Some(Calibration{span})} else {None}
+    }
+
+    pub fn save(&self, port: &str) {
+        fs::write(Calibration::path(port), format!("{}\n", self.span))
+            .expect("Couldn't save flex control calibration");
+    }
 }
@@ -0,0 +1,869 @@
+use crate::arena::Arena;
+use crate::attract::Attract;
+use crate::ball::{Ball, Sounds};
+use crate::collision::Side;
+use crate::player::{Player, PlayerType, PlayerMode};
+use crate::input::{self, Devices, Gamepads, InputOpt};
+use crate::latency::LatencyProbe;
+use crate::netpong::{Goal, Update};
+use crate::netpong::mod_Update::OneOfUpdateType;
+use crate::network::Link;
+use crate::physics::PhysicsConfig;
+use crate::powerup::{Kinds, PowerUps};
+use crate::rules::{Rules, Serve};
+use crate::scoring::{Match, MatchRules};
+use crate::handicap::{self, Handicap};
+use crate::view::Viewport;
+use crate::wacky_tube_man::WackyTubeMan;
+use crate::{check_player, make_player, message, to_player_type, SCREEN_WIDTH, SCREEN_HEIGHT};
+
+use std::time::{Duration, Instant};
+use structopt::StructOpt;
+
+use ggez::input::mouse;
+use ggez::input::gamepad::GamepadId;
+use ggez::event::{Axis, Button};
+use ggez::{
+    event, 
+    graphics, 
+    conf,
+    Context, 
+    GameResult,
+    ContextBuilder
+};
+
+// seconds between one game of a match ending and the next starting
+const NEXT_GAME: f32 = 5.0;
+// how far a knob, stick or mouse has to move a paddle to end the demo
+const WAKE_MOVE: f32 = 10.0;
+
+pub struct MainState {
+    // two to four; the order they were given in is P1, P2, ...
+    players: Vec<Player>,
+    // one for each player, in the same order
+    handicaps: Vec<Handicap>,
+    scoring: Match,
+    since_game_over: f32,
+    balls: Vec<Ball>,
+    sounds: Sounds,
+    rules: Rules,
+    physics: PhysicsConfig,
+    arena: Arena,
+    powerups: PowerUps,
+    // id for the next extra ball; the serve ball is always 0
+    next_ball: u32,
+    // seconds and rally length since the last extra ball joined
+    since_spawn: f32,
+    rally_mark: i32,
+    mode: GameMode,
+    pads: Gamepads,
+    view: Viewport,
+    link: Option<Link>,
+    // paddle positions and serve buttons received from the network, by slot
+    remote: [Option<f32>; 4],
+    remote_serve: [bool; 4],
+    // who served last, when serves belong to players
+    server: Option<Side>,
+    latency: Option<LatencyProbe>,
+    frame_time: f32,
+    wacky: WackyTubeMan,
+    // seconds paused with nobody touching anything, and how many before
+    // the demo starts (0 for never)
+    idle: f32,
+    attract_after: f32,
+    attract: Option<Attract>,
+    // where the human paddles were on the demo's first tick
+    resting: Vec<f32>,
+}
+
+
+#[derive(Copy, Clone)]
+enum GameMode {
+    Calibrating,
+    Paused,
+    Attract,
+    WaitingForNetwork,
+    Active,
+    GameOver,
+}
+
+impl MainState {
+    pub fn new(ctx: &mut Context, opt: Opt) -> GameResult<Self> {
+        let slots = [
+            (Side::Left,   Some(&opt.left)),
+            (Side::Right,  Some(&opt.right)),
+            (Side::Top,    opt.top.as_ref()),
+            (Side::Bottom, opt.bottom.as_ref()),
+        ];
+        let types: Vec<(Side, PlayerType)> = slots.iter()
+            .filter_map(|(side, arg)| arg.map(|arg| (*side, to_player_type(arg))))
+            .collect();
+        let input = opt.input.config();
+        
+        // every network slot shares one link; an address on any of them
+        // means this end connects rather than listens
+        let networked: Vec<&Option<String>> = types.iter()
+            .filter_map(|(_, me)| match me {
+                PlayerType::Network(address) => Some(address),
+                _ => None,
+            })
+            .collect();
+        let link = if networked.is_empty() {
+            None
+        } else {
+            Some(Link::new(networked.iter().find_map(|address| address.as_deref())))
+        };
+        let mode = match &link {
+            Some(link) if link.listening() => GameMode::WaitingForNetwork,
+            _ => GameMode::Paused,
+        };
+        let mut wacky = WackyTubeMan::new(40.0, 6.0, graphics::Color::from_rgb(255, 198, 41));
+        wacky.set_position(200.0, 200.0);
+
+        let mut physics = match &opt.physics {
+            Some(path) => PhysicsConfig::load(path),
+            None => PhysicsConfig::default(),
+        };
+        if let Some(speed) = opt.speed {
+            physics.ball_speed = speed;
+        }
+        if let Some(delay) = opt.serve_delay {
+            physics.serve_delay = delay;
+        }
+
+        let handicaps: Vec<Handicap> = types.iter()
+            .map(|(side, _)| opt.handicap.iter()
+                .find(|(wall, _)| wall == side)
+                .map(|(_, handicap)| *handicap)
+                .unwrap_or_default())
+            .collect();
+        let players: Vec<Player> = types.iter().zip(&handicaps)
+            .map(|((side, me), handicap)| make_player(*side, me, input, &opt.record, handicap, &physics))
+            .collect();
+        let mode = if players.iter().any(Player::calibrating) {GameMode::Calibrating} else {mode};
+        let (width, height) = graphics::drawable_size(ctx);
+        let rules = Rules{
+            spin: opt.spin,
+            extra_ball_hits: opt.multiball_hits,
+            extra_ball_every: opt.multiball_every,
+            max_balls: opt.max_balls,
+            powerups: opt.powerups.as_deref().map(Kinds::parse).unwrap_or_default(),
+            powerup_every: opt.powerup_every,
+            serve: opt.serve,
+            serve_timeout: opt.serve_timeout,
+        };
+        let sounds = Sounds::new(ctx);
+        let mut ball = Ball::new(ctx, 0, &sounds, rules, physics);
+        let server = match rules.serve {
+            Serve::Centre => None,
+            _ => Some(players[0].side()),
+        };
+        if let Some(side) = server {
+            ball.hold(side);
+        }
+
+        let scoring = Match::new(MatchRules{
+            points: opt.points,
+            win_by: opt.win_by,
+            games: opt.games,
+            switch_sides: opt.switch_sides,
+        }, players.len());
+
+        let mut state = MainState {
+            players,
+            handicaps,
+            scoring,
+            since_game_over: 0.0,
+            balls: vec![ball],
+            sounds,
+            rules,
+            physics,
+            arena: Arena::load(&opt.arena),
+            powerups: PowerUps::new(rules.powerups, rules.powerup_every),
+            next_ball: 1,
+            since_spawn: 0.0,
+            rally_mark: 0,
+            mode,
+            pads: Gamepads::default(),
+            view: Viewport::fit(ctx, width, height)?,
+            link,
+            remote: [None; 4],
+            remote_serve: [false; 4],
+            server,
+            latency: if opt.latency {Some(LatencyProbe::start())} else {None},
+            frame_time: opt.frame_time,
+            wacky,
+            idle: 0.0,
+            attract_after: opt.attract,
+            attract: None,
+            resting: vec![],
+        };
+        state.head_starts();
+        Ok(state)
+    }
+
+    fn present(&mut self, ctx: &mut Context) -> GameResult {
+        graphics::present(ctx)?;
+        if let Some(probe) = &mut self.latency {
+            probe.presented();
+        }
+        Ok(())
+    }
+
+    // the side that owns the balls; the other end follows its ball messages
+    fn is_host(&self) -> bool {
+        match &self.link {
+            Some(link) => link.listening(),
+            None => true,
+        }
+    }
+
+    fn spawn_ball(&mut self, ctx: &mut Context, id: u32) -> &mut Ball {
+        self.next_ball = self.next_ball.max(id + 1);
+        self.balls.push(Ball::new(ctx, id, &self.sounds, self.rules, self.physics));
+        self.balls.last_mut().unwrap()
+    }
+
+    // add another ball once the rally or the timer says so
+    fn multiball(&mut self, ctx: &mut Context, dt: f32) {
+        let rally = self.balls.iter().map(Ball::rally).max().unwrap_or(0);
+        self.rally_mark = self.rally_mark.min(rally);
+        if self.balls.len() >= self.rules.max_balls {
+            self.since_spawn = 0.0;
+            return;
+        }
+        self.since_spawn += dt;
+        let by_hits = matches!(self.rules.extra_ball_hits, Some(hits) if rally >= self.rally_mark + hits);
+        let by_time = matches!(self.rules.extra_ball_every, Some(every) if self.since_spawn >= every);
+        if by_hits || by_time {
+            self.spawn_ball(ctx, self.next_ball);
+            self.since_spawn = 0.0;
+            self.rally_mark = rally;
+        }
+    }
+
+    // exchange paddles with the other end and pick up its ball positions
+    fn sync(&mut self, ctx: &mut Context) {
+        let updates = match &mut self.link {
+            Some(link) => link.receive(),
+            None => return,
+        };
+        let host = self.is_host();
+        let mut goals = vec![];
+        for update in updates {
+            match update.UpdateType {
+                OneOfUpdateType::paddle(paddle) => {
+                    let slot = paddle.slot as usize;
+                    if slot < self.remote.len() {
+                        self.remote[slot] = Some(paddle.y);
+                        self.remote_serve[slot] = paddle.serve;
+                    }
+                },
+                OneOfUpdateType::ball(message) if !host => {
+                    match self.balls.iter_mut().find(|ball| ball.id == message.id) {
+                        Some(ball) => ball.apply(&message),
+                        None => self.spawn_ball(ctx, message.id).apply(&message),
+                    }
+                },
+                OneOfUpdateType::powerup(message) if !host => self.powerups.apply(&message),
+                OneOfUpdateType::goal(message) if !host => goals.push(message),
+                _ => (),
+            }
+        }
+        for message in goals {
+            let side = match Side::ALL.get(message.slot as usize) {
+                Some(&side) => side,
+                None => continue,
+            };
+            let last_hit = (message.last_hit as usize).checked_sub(1).and_then(|i| Side::ALL.get(i)).copied();
+            self.balls.retain(|ball| ball.id == 0 || ball.id != message.ball);
+            self.goal(side, last_hit, message.ball);
+        }
+        if let Some(link) = &self.link {
+            for player in self.players.iter().filter(|p| p.is_local()) {
+                link.send(&player.paddle_message());
+            }
+        }
+    }
+
+    // the host's balls and power-ups
+    fn send_state(&self) {
+        if let Some(link) = &self.link {
+            for ball in &self.balls {
+                link.send(&Update{UpdateType: OneOfUpdateType::ball(ball.to_message())});
+            }
+            for powerup in self.powerups.to_messages() {
+                link.send(&Update{UpdateType: OneOfUpdateType::powerup(powerup)});
+            }
+        }
+    }
+
+    fn send_goal(&self, side: Side, last_hit: Option<Side>, id: u32) {
+        if let Some(link) = &self.link {
+            link.send(&Update{UpdateType: OneOfUpdateType::goal(Goal{
+                slot: side as u32,
+                last_hit: last_hit.map_or(0, |hit| hit as u32 + 1),
+                ball: id,
+            })});
+        }
+    }
+
+    // a ball went out by `side`; the serve ball comes back into play
+    fn goal(&mut self, side: Side, last_hit: Option<Side>, id: u32) {
+        if let GameMode::GameOver = self.mode {
+            return;
+        }
+        self.score(side, last_hit);
+        if id == 0 {
+            self.next_serve(side, last_hit);
+        }
+    }
+
+    // power-ups the balls ran over, and what the running ones do
+    fn apply_powerups(&mut self, ctx: &mut Context, dt: f32) {
+        let host = self.is_host();
+        self.powerups.update(dt, host);
+        if host {
+            // an extra ball picked up with the field already full is lost
+            for _ in 0..self.powerups.collect(&self.balls) {
+                if self.balls.len() < self.rules.max_balls {
+                    self.spawn_ball(ctx, self.next_ball);
+                }
+            }
+        }
+        for player in &mut self.players {
+            player.set_scale(self.powerups.paddle_scale(player.side()));
+        }
+        // the player whose half a ball is in sets its handicap pace
+        let paces: Vec<f32> = self.balls.iter()
+            .map(|ball| self.players.iter().zip(&self.handicaps)
+                .min_by(|a, b| a.0.side().depth(ball.pos).total_cmp(&b.0.side().depth(ball.pos)))
+                .map(|(_, handicap)| handicap.pace)
+                .unwrap_or(1.0))
+            .collect();
+        for (ball, handicap) in self.balls.iter_mut().zip(paces) {
+            ball.pace = self.powerups.pace(ball.id) * handicap;
+            ball.hidden = self.powerups.hidden(ball.id);
+        }
+    }
+
+    // the goals still open, one for each player who is not out yet
+    fn goals(&self) -> Vec<Side> {
+        self.players.iter()
+            .filter(|player| !self.scoring.is_out(player.conceded))
+            .map(Player::side)
+            .collect()
+    }
+
+    fn points(&self) -> Vec<i32> {
+        self.players.iter().map(|player| player.score).collect()
+    }
+
+    // A ball went out through `side`. The point goes to whoever hit it
+    // last, or with two players simply to the other one. With more, letting
+    // in enough goals knocks a player out and closes their goal.
+    fn score(&mut self, side: Side, last_hit: Option<Side>) {
+        let two_player = self.players.len() == 2;
+        for player in &mut self.players {
+            if player.side() == side {
+                player.conceded += 1;
+                if self.scoring.is_out(player.conceded) {
+                    player.set_mode(PlayerMode::Loser);
+                }
+            } else if two_player || last_hit == Some(player.side()) {
+                player.update_score(1);
+            }
+        }
+
+        let points = self.points();
+        let conceded: Vec<i32> = self.players.iter().map(|player| player.conceded).collect();
+        if let Some(winner) = self.scoring.game_winner(&points, &conceded) {
+            for (i, player) in self.players.iter_mut().enumerate() {
+                player.set_mode(if i == winner {PlayerMode::Winner} else {PlayerMode::Loser});
+            }
+            self.scoring.finish_game(winner, points);
+            for ball in &mut self.balls {
+                ball.game_over();
+            }
+            self.balls.retain(|ball| ball.id == 0);
+            self.powerups.clear();
+            self.since_game_over = 0.0;
+            self.mode = GameMode::GameOver;
+        }
+    }
+
+    // Handicap head starts: points with two players, and with more, goals
+    // that can be let in before going out.
+    fn head_starts(&mut self) {
+        let two_player = self.players.len() == 2;
+        for (player, handicap) in self.players.iter_mut().zip(&self.handicaps) {
+            if two_player {
+                player.score = handicap.start;
+            } else {
+                player.conceded = -handicap.start;
+            }
+        }
+    }
+
+    // under each player's score, any handicap they are playing with
+    fn draw_handicaps(&self, ctx: &mut Context) -> GameResult {
+        for (player, handicap) in self.players.iter().zip(&self.handicaps) {
+            if let Some(note) = handicap.describe() {
+                let pos = match player.side() {
+                    Side::Left   => [SCREEN_WIDTH * 0.25, 110.0],
+                    Side::Right  => [SCREEN_WIDTH * 0.75, 110.0],
+                    Side::Top    => [SCREEN_WIDTH * 0.5 + 80.0, SCREEN_HEIGHT * 0.25 - 32.0],
+                    Side::Bottom => [SCREEN_WIDTH * 0.5 + 80.0, SCREEN_HEIGHT * 0.75 - 32.0],
+                };
+                graphics::draw(ctx, &graphics::Text::new(note), graphics::DrawParam::default().dest(pos))?;
+            }
+        }
+        Ok(())
+    }
+
+    // on to the next game of the match, or a new match once it is won
+    fn next_game(&mut self, ctx: &mut Context) {
+        if self.scoring.winner().is_some() {
+            self.scoring.restart();
+            for player in &mut self.players {
+                player.new_match();
+            }
+        }
+        for player in &mut self.players {
+            player.restart();
+            if self.scoring.rules.switch_sides {
+                player.set_side(player.side().opposite());
+            }
+        }
+        self.head_starts();
+        self.balls = vec![Ball::new(ctx, 0, &self.sounds, self.rules, self.physics)];
+        self.next_ball = 1;
+        // the first serve goes round the players game by game
+        let first = self.players[self.scoring.played() % self.players.len()].side();
+        self.server = None;
+        if self.rules.serve != Serve::Centre {
+            self.balls[0].hold(first);
+            self.server = Some(first);
+        }
+        self.mode = GameMode::Active;
+    }
+
+    // the next player still in the game after `after`, going round the walls
+    fn next_standing(&self, after: Side) -> Option<Side> {
+        let goals = self.goals();
+        let i = Side::ALL.iter().position(|&side| side == after).unwrap();
+        (1..=Side::ALL.len())
+            .map(|k| Side::ALL[(i + k) % Side::ALL.len()])
+            .find(|side| goals.contains(side))
+    }
+
+    // After the serve ball goes out through `side`, the serve rule picks
+    // who puts it back in play. A pick who is out passes it on.
+    fn next_serve(&mut self, side: Side, last_hit: Option<Side>) {
+        let pick = match self.rules.serve {
+            Serve::Centre    => return,
+            Serve::Loser     => Some(side),
+            Serve::Winner    => last_hit.filter(|&hit| hit != side),
+            Serve::Alternate => self.server.and_then(|server| self.next_standing(server)),
+        };
+        let goals = self.goals();
+        let server = pick.filter(|pick| goals.contains(pick)).or_else(|| self.next_standing(side));
+        if let (Some(server), Some(ball)) = (server, self.balls.iter_mut().find(|ball| ball.id == 0)) {
+            ball.hold(server);
+            self.server = Some(server);
+        }
+    }
+
+    fn start_attract(&mut self) {
+        self.resting = vec![];
+        self.attract = Some(Attract::new(self.physics));
+        self.mode = GameMode::Attract;
+    }
+
+    // back to the paused screen, ready for someone to play
+    fn wake(&mut self) {
+        self.attract = None;
+        self.idle = 0.0;
+        self.mode = GameMode::Paused;
+    }
+
+    fn toggle_pause(&mut self, ctx: &mut Context) {
+        match self.mode {
+            GameMode::Calibrating => {
+                for player in &mut self.players {
+                    player.finish_calibration();
+                }
+                self.mode = GameMode::Paused;
+            },
+            GameMode::Paused => {
+                self.mode = GameMode::Active;
+                mouse::set_cursor_grabbed(ctx, true).unwrap();
+                mouse::set_cursor_hidden(ctx, true);
+            },
+            GameMode::Active => {
+                self.mode = GameMode::Paused;
+                mouse::set_cursor_grabbed(ctx, false).unwrap();
+                mouse::set_cursor_hidden(ctx, false);
+            },
+            GameMode::GameOver => self.next_game(ctx),
+            _ => (),
+        }
+    }
+}
+
+impl event::EventHandler for MainState {
+    fn key_up_event(&mut self, 
+        ctx: &mut Context, 
+        keycode: event::KeyCode, _keymods: event::KeyMods) {
+        if let GameMode::Attract = self.mode {
+            self.wake();
+            return;
+        }
+        if keycode == event::KeyCode::Space {
+            self.toggle_pause(ctx);
+        }
+    }
+
+    fn key_down_event(&mut self,
+        _ctx: &mut Context,
+        keycode: event::KeyCode, _keymods: event::KeyMods, repeat: bool) {
+        self.idle = 0.0;
+        if let Some(probe) = &mut self.latency {
+            let paddle_key = Side::ALL.iter().any(|&side| {
+                let (up, down) = input::keys(side);
+                keycode == up || keycode == down
+            });
+            if !repeat && paddle_key {
+                probe.input(Instant::now());
+            }
+        }
+    }
+
+    fn quit_event(&mut self, _ctx: &mut Context) -> bool {
+        if let Some(probe) = &self.latency {
+            println!("{}", probe.report());
+        }
+        false
+    }
+
+    fn mouse_motion_event(&mut self, _ctx: &mut Context, _x: f32, _y: f32, _dx: f32, _dy: f32) {
+        self.idle = 0.0;
+    }
+
+    fn gamepad_button_down_event(&mut self, _ctx: &mut Context, btn: Button, id: GamepadId) {
+        self.pads.button(btn, true, id);
+        self.idle = 0.0;
+    }
+
+    fn gamepad_button_up_event(&mut self, ctx: &mut Context, btn: Button, id: GamepadId) {
+        self.pads.button(btn, false, id);
+        if let GameMode::Attract = self.mode {
+            self.wake();
+            return;
+        }
+        if btn == Button::Start || btn == Button::Select {
+            self.toggle_pause(ctx);
+        }
+    }
+
+    fn resize_event(&mut self, ctx: &mut Context, width: f32, height: f32) {
+        self.view.refit(ctx, width, height).expect("Couldn't rescale the playfield");
+    }
+
+    fn gamepad_axis_event(&mut self, _ctx: &mut Context, axis: Axis, value: f32, id: GamepadId) {
+        self.pads.axis(axis, value, id);
+        if value.abs() > 0.5 {
+            self.idle = 0.0;
+        }
+    }
+
+    fn update(&mut self, ctx: &mut Context) -> GameResult {
+
+        let dt = ggez::timer::delta(ctx).as_secs_f32();
+        ggez::timer::sleep(Duration::from_secs_f32((self.frame_time - dt).max(0.0)));
+        self.wacky.update(dt);
+        self.sync(ctx);
+        let on_field: Vec<_> = self.players.iter().map(Player::paddle).collect();
+        let mut scores = [0; 4];
+        for player in &self.players {
+            scores[player.side() as usize] = player.score;
+        }
+        let devices = Devices{
+            pads: &self.pads,
+            view: &self.view,
+            remote: self.remote,
+            remote_serve: self.remote_serve,
+            paddles: &on_field,
+            scores,
+        };
+        match self.mode {
+            GameMode::Paused => {
+                self.idle += dt;
+                if self.attract_after > 0.0 && self.idle >= self.attract_after {
+                    self.start_attract();
+                }
+                return Ok(())
+            },
+            GameMode::Attract => {
+                // the demo ends as soon as anyone moves a paddle
+                for player in self.players.iter_mut().filter(|player| player.is_human()) {
+                    player.update(Some(ctx), dt, &devices, &[]);
+                }
+                // a mouse paddle jumps to the pointer on its first poll,
+                // so that is where it rests
+                let moved = self.players.iter().zip(&self.resting)
+                    .any(|(player, rest)| player.is_human() && (player.along() - rest).abs() > WAKE_MOVE);
+                if self.resting.is_empty() {
+                    self.resting = self.players.iter().map(Player::along).collect();
+                }
+                if moved {
+                    self.wake();
+                } else if let Some(attract) = &mut self.attract {
+                    attract.update(dt);
+                }
+                return Ok(())
+            },
+            GameMode::Calibrating => {
+                for player in &mut self.players {
+                    player.update(Some(ctx), dt, &devices, &self.balls);
+                }
+                return Ok(())
+            },
+            GameMode::GameOver => {
+                for player in &mut self.players {
+                    player.update(Some(ctx), dt, &devices, &self.balls);
+                }
+                self.since_game_over += dt;
+                if self.scoring.winner().is_none() && self.since_game_over >= NEXT_GAME {
+                    self.next_game(ctx);
+                }
+                return Ok(())
+            },
+            GameMode::WaitingForNetwork => {
+                if matches!(&self.link, Some(link) if link.connected()) {
+                    self.mode = GameMode::Paused;
+                }
+                return Ok(())},
+            _ => ()
+        };
+
+        if dt < 0.1 {
+            for player in &mut self.players {
+                player.update(Some(ctx), dt, &devices, &self.balls);
+            }
+            if let Some(probe) = &mut self.latency {
+                for read in self.players.iter_mut().filter_map(Player::take_serial_read) {
+                    probe.serial(read);
+                }
+            }
+
+            for ball in &mut self.balls {
+                let server = ball.server();
+                if self.players.iter().any(|player| Some(player.side()) == server && player.serving()) {
+                    ball.release();
+                }
+            }
+            if self.is_host() {
+                self.multiball(ctx, dt);
+            }
+            self.apply_powerups(ctx, dt);
+            for player in &mut self.players {
+                player.anticipate(&self.balls, dt);
+            }
+            self.arena.update(dt);
+            let goals = self.goals();
+            let paddles: Vec<_> = self.players.iter()
+                .filter(|player| goals.contains(&player.side()))
+                .map(Player::paddle)
+                .collect();
+            let mut goals_scored = vec![];
+            for ball in &mut self.balls {
+                let last_hit = ball.last_hit();
+                let goal = ball.update(dt, &paddles, &goals, &self.arena);
+                ball.play(ctx);
+                if let Some(side) = goal {
+                    goals_scored.push((side, last_hit, ball.id));
+                }
+            }
+            // a client only guesses where the balls go; the host says what
+            // scored and which extra balls leave play
+            if self.is_host() {
+                // extra balls leave play once they score; the serve ball stays
+                self.balls.retain(|ball| ball.id == 0 || !goals_scored.iter().any(|&(_, _, id)| id == ball.id));
+                self.send_state();
+                for (side, last_hit, id) in goals_scored {
+                    self.send_goal(side, last_hit, id);
+                    self.goal(side, last_hit, id);
+                }
+            }
+        }
+        Ok(())
+    }
+
+
+    fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        self.view.clear(ctx)?;
+
+        match self.mode {
+            GameMode::Paused => {
+                self.wacky.draw(ctx, false)?;
+                // message(ctx, "Game paused. Hit [space] to continue.\n[Esc] to quit.")?;
+                self.present(ctx)?;
+                return Ok(())
+            },
+            GameMode::Attract => {
+                if let Some(attract) = &self.attract {
+                    attract.draw(ctx)?;
+                }
+                self.present(ctx)?;
+                return Ok(())
+            },
+            GameMode::Calibrating => {
+                message(ctx, "Turn each knob to its top stop, then its bottom stop.\nKnobs set up before only need the top stop.\nHit [space] when done.")?;
+                self.present(ctx)?;
+                return Ok(())
+            },
+            GameMode::WaitingForNetwork => {
+                message(ctx, "Waiting for network player")?;
+                self.present(ctx)?;
+                return Ok(())
+            },
+            GameMode::GameOver => {
+                for player in &self.players {
+                    player.draw(ctx)?;
+                    player.draw_score(ctx)?;
+                }
+                let next = if self.scoring.winner().is_some() {"a new match"} else {"the next game"};
+                message(ctx, &format!("{}\n\nHit [space] for {}.", self.scoring.summary(), next))?;
+                self.present(ctx)?;
+                return Ok(())
+            },
+            _ => ()
+        }; 
+
+        let mid_line = graphics::Mesh::new_line(
+            ctx, 
+            &[[SCREEN_WIDTH/2.0, 0.0], [SCREEN_WIDTH/2.0, SCREEN_HEIGHT]], 
+            2.0, graphics::WHITE)?;
+        graphics::draw(ctx, &mid_line, graphics::DrawParam::default())?;
+        self.arena.draw(ctx)?;
+        self.powerups.draw(ctx)?;
+        
+        for player in &self.players {
+            player.draw(ctx)?;
+        }
+        for ball in &self.balls {
+            ball.draw(ctx)?;
+        }
+        for player in &self.players {
+            player.draw_score(ctx)?;
+        }
+        self.draw_handicaps(ctx)?;
+        if let Some(call) = self.scoring.call(&self.points()) {
+            let text = graphics::Text::new(call);
+            let r = text.dimensions(ctx);
+            graphics::draw(ctx, &text, graphics::DrawParam::default().dest([(SCREEN_WIDTH - r.w)/2.0, 120.0]))?;
+        }
+
+        self.present(ctx)?;
+        Ok(())
+    }
+}
+
+#[derive(StructOpt, Debug)]
+#[structopt(name = "netpong")]
+pub struct Opt {
+    #[structopt(short, long)]
+    debug: bool,
+    /// Serve speed along each axis in pixels per second (overrides the physics file)
+    #[structopt(short, long)]
+    speed: Option<f32>,
+    /// TOML file of physics parameters; see PhysicsConfig for the fields
+    #[structopt(long)]
+    physics: Option<String>,
+    #[structopt(flatten)]
+    input: InputOpt,
+    /// Play with spin: moving paddles curve the ball
+    #[structopt(long)]
+    spin: bool,
+    /// Multi-ball: add a ball after this many consecutive hits
+    #[structopt(long)]
+    multiball_hits: Option<i32>,
+    /// Multi-ball: add a ball every this many seconds
+    #[structopt(long)]
+    multiball_every: Option<f32>,
+    /// Most balls in play at once, from multi-ball or extra-ball power-ups
+    #[structopt(long, default_value = "3")]
+    max_balls: usize,
+    /// Report input-to-photon and serial read latency percentiles
+    #[structopt(long)]
+    latency: bool,
+    /// Target seconds per frame for the frame limiter
+    #[structopt(long, default_value = "0.016666")]
+    frame_time: f32,
+    /// Record each paddle's inputs to <prefix>.<side>, e.g. <prefix>.left
+    #[structopt(long)]
+    record: Option<String>,
+    /// Obstacles: classic, pillars, gates, bumpers, portals, or an arena file
+    #[structopt(long, default_value = "classic")]
+    arena: String,
+    /// Power-ups to play with: all, or a list of grow,shrink,slow,invisible,extra
+    #[structopt(long)]
+    powerups: Option<String>,
+    /// Seconds between power-ups appearing
+    #[structopt(long, default_value = "8.0")]
+    powerup_every: f32,
+    /// Who serves after a point: centre, alternate, loser or winner
+    #[structopt(long, default_value = "centre")]
+    serve: Serve,
+    /// Seconds before each serve (overrides the physics file)
+    #[structopt(long)]
+    serve_delay: Option<f32>,
+    /// Seconds a server can hold the ball before it is served for them
+    #[structopt(long, default_value = "3.0")]
+    serve_timeout: f32,
+    /// Points to win a game; with three or more players, goals let in before a player is out
+    #[structopt(long, default_value = "3")]
+    points: i32,
+    /// Lead needed to win a game (2 plays deuce)
+    #[structopt(long, default_value = "1")]
+    win_by: i32,
+    /// Play a match of the best of this many games
+    #[structopt(long, default_value = "1")]
+    games: i32,
+    /// Change ends after every game
+    #[structopt(long)]
+    switch_sides: bool,
+    /// Handicap for the player starting on a side, e.g. left:height=1.5,pace=0.8,start=2,assist=0.3
+    #[structopt(long, number_of_values = 1, parse(try_from_str = handicap::parse))]
+    handicap: Vec<(Side, Handicap)>,
+    /// Seconds paused with no input before two computers play a demo (0 for never)
+    #[structopt(long, default_value = "30")]
+    attract: f32,
+    /// Third player, on the top wall
+    #[structopt(long, validator = check_player)]
+    top: Option<String>,
+    /// Fourth player, on the bottom wall
+    #[structopt(long, validator = check_player)]
+    bottom: Option<String>,
+    #[structopt(default_value = "man", validator = check_player)]
+    left: String,
+    #[structopt(default_value = "computer", validator = check_player)]
+    right: String,
+}
+
+// opens the game window and runs the state `start` makes in it
+pub fn window<S: event::EventHandler + 'static>(start: impl FnOnce(&mut Context) -> GameResult<S>) -> GameResult {
+    let (mut ctx, event_loop) = ContextBuilder::new("netpong", "ahenshaw")
+            .window_mode(
+                conf::WindowMode::default()
+                    .resizable(true) 
+                    .maximized(true)
+                    .fullscreen_type(conf::FullscreenType::Windowed)
+            )
+            .build()?;
+
+    graphics::set_window_title(&ctx, "Net Pong");
+    let state = start(&mut ctx)?;
+    event::run(ctx, event_loop, state)
+}
@@ -0,0 +1,197 @@
+use std::cell::Cell;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+use structopt::StructOpt;
+
+use crate::{check_player, to_player_type, SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::collision::Side;
+use crate::input::{Command, Frame, PaddleInput, KEY_SPEED};
+use crate::physics::PhysicsConfig;
+use crate::player::PlayerType;
+use crate::random;
+use crate::scoring::{Match, MatchRules};
+use crate::sim::{self, Sim};
+
+// ball x, y, vx, vy, then the agent's paddle and the opponent's, all
+// scaled to roughly -1.0..=1.0
+pub type Observation = [f32; 6];
+
+// The learner's paddle, moved by whatever the last action was.
+pub struct AgentInput {
+    action: Rc<Cell<f32>>,
+}
+
+impl AgentInput {
+    pub fn new(action: Rc<Cell<f32>>) -> Self {
+        AgentInput{action}
+    }
+}
+
+impl PaddleInput for AgentInput {
+    fn poll(&mut self, frame: &Frame) -> Command {
+        let action = self.action.get().clamp(-1.0, 1.0);
+        Command::Move(action * KEY_SPEED * frame.dt)
+    }
+
+    fn serve(&mut self, _frame: &Frame) -> bool {
+        true
+    }
+}
+
+// A reinforcement learning environment: an agent on the left plays one
+// game against `opponent` on the right. Each step takes an action from
+// -1.0 (full speed up) to 1.0 (full speed down), plays `frame_skip` ticks
+// with it and scores +1 for every point the agent wins and -1 for every
+// point it loses. An episode is one game, cut short after `max_time`
+// simulated seconds.
+pub struct Env {
+    opponent: PlayerType,
+    physics: PhysicsConfig,
+    points: i32,
+    frame_skip: u32,
+    max_time: f32,
+    action: Rc<Cell<f32>>,
+    sim: Sim,
+}
+
+impl Env {
+    pub fn new(opponent: PlayerType, physics: PhysicsConfig, points: i32, frame_skip: u32, max_time: f32) -> Self {
+        let action = Rc::new(Cell::new(0.0));
+        let sim = Env::start(&opponent, &action, &physics, points);
+        Env{opponent, physics, points, frame_skip: frame_skip.max(1), max_time, action, sim}
+    }
+
+    fn start(opponent: &PlayerType, action: &Rc<Cell<f32>>, physics: &PhysicsConfig, points: i32) -> Sim {
+        let players = vec![
+            sim::player(Side::Left, &PlayerType::Agent(action.clone()), physics),
+            sim::player(Side::Right, opponent, physics),
+        ];
+        let rules = MatchRules{points, win_by: 1, games: 1, switch_sides: false};
+        Sim::new(players, Match::new(rules, 2), *physics)
+    }
+
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        random::seed(seed);
+        self.action.set(0.0);
+        self.sim = Env::start(&self.opponent, &self.action, &self.physics, self.points);
+        self.observe()
+    }
+
+    pub fn step(&mut self, action: f32) -> (Observation, f32, bool) {
+        self.action.set(action);
+        let mut reward = 0.0;
+        let mut done = false;
+        for _ in 0..self.frame_skip {
+            match self.sim.step() {
+                Some(Side::Left) => reward -= 1.0,
+                Some(_) => reward += 1.0,
+                None => (),
+            }
+            done = self.sim.scoring.played() > 0 || self.sim.time >= self.max_time;
+            if done {
+                break;
+            }
+        }
+        (self.observe(), reward, done)
+    }
+
+    fn observe(&self) -> Observation {
+        let ball = self.sim.ball();
+        let speed = self.physics.ball_speed;
+        let players = self.sim.players();
+        let paddle = |i: usize| players[i].along() / SCREEN_HEIGHT * 2.0 - 1.0;
+        [
+            ball.pos.x / SCREEN_WIDTH * 2.0 - 1.0,
+            ball.pos.y / SCREEN_HEIGHT * 2.0 - 1.0,
+            ball.vel.x / speed,
+            ball.vel.y / speed,
+            paddle(0),
+            paddle(1),
+        ]
+    }
+}
+
+// netpong gym [options]
+#[derive(StructOpt, Debug)]
+#[structopt(name = "gym")]
+pub struct GymOpt {
+    /// Port to listen on, on this machine only
+    #[structopt(long, default_value = "34522")]
+    port: u16,
+    /// Who the agent plays: computer[:level], script:<file> or bot:<command>
+    #[structopt(long, default_value = "computer", validator = check_player)]
+    opponent: String,
+    /// Ticks each action is held for
+    #[structopt(long, default_value = "4")]
+    frame_skip: u32,
+    /// Points to win an episode
+    #[structopt(long, default_value = "5")]
+    points: i32,
+    /// Simulated seconds after which an episode ends anyway
+    #[structopt(long, default_value = "300")]
+    max_time: f32,
+    /// TOML file of physics parameters
+    #[structopt(long)]
+    physics: Option<String>,
+}
+
+// One request per line from the training script:
+//   {"cmd": "reset", "seed": 7}
+//   {"cmd": "step", "action": -0.5}
+#[derive(Deserialize)]
+#[serde(tag = "cmd", rename_all = "lowercase")]
+enum Request {
+    Reset{#[serde(default)] seed: u64},
+    Step{action: f32},
+}
+
+// and one reply line to each, or {"error": "..."} if it made no sense
+#[derive(Serialize)]
+struct Reply {
+    observation: Observation,
+    reward: f32,
+    done: bool,
+}
+
+#[derive(Serialize)]
+struct Failure {
+    error: String,
+}
+
+// Serves the environment over TCP to one training script at a time.
+pub fn serve(opt: GymOpt) {
+    let opponent = match to_player_type(&opt.opponent) {
+        PlayerType::Human(_) | PlayerType::Network(_) => panic!("{} can't play in the gym", opt.opponent),
+        opponent => opponent,
+    };
+    let physics = match &opt.physics {
+        Some(path) => PhysicsConfig::load(path),
+        None => PhysicsConfig::default(),
+    };
+    let mut env = Env::new(opponent, physics, opt.points, opt.frame_skip, opt.max_time);
+
+    let listener = TcpListener::bind(("127.0.0.1", opt.port)).expect("Can't listen for the gym");
+    println!("gym listening on 127.0.0.1:{}", opt.port);
+    for stream in listener.incoming().map_while(Result::ok) {
+        let mut out = stream.try_clone().expect("Can't write to gym client");
+        for line in BufReader::new(stream).lines().map_while(Result::ok) {
+            let text = match serde_json::from_str(&line) {
+                Ok(Request::Reset{seed}) => serde_json::to_string(&Reply{observation: env.reset(seed), reward: 0.0, done: false}),
+                Ok(Request::Step{action}) => {
+                    let (observation, reward, done) = env.step(action);
+                    serde_json::to_string(&Reply{observation, reward, done})
+                },
+                Err(err) => {
+                    eprintln!("gym: {}: {}", err, line);
+                    serde_json::to_string(&Failure{error: err.to_string()})
+                },
+            }.expect("Couldn't write gym reply");
+            if writeln!(out, "{}", text).is_err() {
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use crate::ai::{ComputerInput, Level};
+use crate::collision::Side;
+use crate::flexcontrol::SerialRead;
+use crate::input::{Command, Frame, PaddleInput, KEY_SPEED};
+use crate::physics::PhysicsConfig;
+
+// Ways to even up a game between players of different skill. A handicap
+// belongs to a player and goes with them if they change ends.
+#[derive(Clone, Copy, Debug)]
+pub struct Handicap {
+    // paddle length as a multiple of the usual
+    pub height: f32,
+    // ball speed while it is in this player's half, as a multiple
+    pub pace: f32,
+    // points, or with three or more players goals that may be let in,
+    // they start every game with
+    pub start: i32,
+    // how hard a human paddle is pulled towards where the ball is going,
+    // as a fraction of keyboard speed
+    pub assist: f32,
+}
+
+impl Default for Handicap {
+    fn default() -> Self {
+        Handicap{height: 1.0, pace: 1.0, start: 0, assist: 0.0}
+    }
+}
+
+impl Handicap {
+    // a short note for the screen, if there is anything to note
+    pub fn describe(&self) -> Option<String> {
+        let mut notes = vec![];
+        if self.height != 1.0 {
+            notes.push(format!("paddle x{}", self.height));
+        }
+        if self.pace != 1.0 {
+            notes.push(format!("ball x{}", self.pace));
+        }
+        if self.start != 0 {
+            notes.push(format!("{:+} start", self.start));
+        }
+        if self.assist > 0.0 {
+            notes.push(format!("assist {}%", (self.assist * 100.0).round()));
+        }
+        if notes.is_empty() {None} else {Some(notes.join("\n"))}
+    }
+}
+
+// <side>:<name>=<value>,... for example left:height=1.5,pace=0.8,start=2,assist=0.3
+pub fn parse(s: &str) -> Result<(Side, Handicap), String> {
+    let (side, list) = s.split_once(':').ok_or(format!("handicap '{}' needs a side, like left:height=1.5", s))?;
+    let side = *Side::ALL.iter()
+        .find(|wall| wall.name() == side)
+        .ok_or(format!("unknown side '{}', try left, right, top or bottom", side))?;
+    let mut handicap = Handicap::default();
+    for item in list.split(',') {
+        let (name, value) = item.split_once('=').ok_or(format!("handicap '{}' needs a value", item))?;
+        let bad = || format!("bad value for {}: {}", name, value);
+        match name {
+            "height" => handicap.height = value.parse().map_err(|_| bad())?,
+            "pace"   => handicap.pace = value.parse().map_err(|_| bad())?,
+            "start"  => handicap.start = value.parse().map_err(|_| bad())?,
+            "assist" => handicap.assist = value.parse().map_err(|_| bad())?,
+            _ => return Err(format!("unknown handicap '{}', try height, pace, start or assist", name)),
+        }
+    }
+    if handicap.height <= 0.0 || handicap.pace <= 0.0 {
+        return Err(format!("handicap height and pace must be above zero in '{}'", s));
+    }
+    handicap.assist = handicap.assist.clamp(0.0, 1.0);
+    Ok((side, handicap))
+}
+
+// how far a command moves a paddle that is `along` its wall
+fn distance(command: Command, along: f32) -> f32 {
+    match command {
+        Command::Hold    => 0.0,
+        Command::Move(d) => d,
+        Command::Goto(a) => a - along,
+    }
+}
+
+// A human controller nudged towards where the ball will reach the paddle.
+// The player stays in charge: the nudge is never faster than `strength`
+// times keyboard speed, only while the ball is on its way, and only to
+// meet it, never to aim the return.
+pub struct AssistInput {
+    inner: Box<dyn PaddleInput>,
+    guide: ComputerInput,
+    strength: f32,
+}
+
+impl AssistInput {
+    pub fn new(inner: Box<dyn PaddleInput>, strength: f32, physics: &PhysicsConfig) -> Self {
+        AssistInput{inner, guide: ComputerInput::new(Level::Perfect, physics), strength}
+    }
+}
+
+impl PaddleInput for AssistInput {
+    fn poll(&mut self, frame: &Frame) -> Command {
+        let along = frame.side.along(frame.paddle);
+        let mine = distance(self.inner.poll(frame), along);
+        let nudge = match self.guide.intercept(frame) {
+            Some(intercept) => intercept - along,
+            None => return Command::Move(mine),
+        };
+        let limit = self.strength * KEY_SPEED * frame.dt;
+        Command::Move(mine + nudge.max(-limit).min(limit))
+    }
+
+    fn serve(&mut self, frame: &Frame) -> bool {
+        self.inner.serve(frame)
+    }
+
+    fn calibrating(&self) -> bool {
+        self.inner.calibrating()
+    }
+
+    fn finish_calibration(&mut self) {
+        self.inner.finish_calibration();
+    }
+
+    fn new_match(&mut self) {
+        self.inner.new_match();
+    }
+
+    fn take_serial_read(&mut self) -> Option<SerialRead> {
+        self.inner.take_serial_read()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Position, Velocity};
+    use crate::collision::{Paddle, Rect};
+    use crate::input::Gamepads;
+    use crate::view::Viewport;
+
+    struct Still;
+
+    impl PaddleInput for Still {
+        fn poll(&mut self, _frame: &Frame) -> Command {
+            Command::Hold
+        }
+    }
+
+    // an opponent in the middle of the right wall, for the computer to aim
+    // away from
+    const OPPONENT: [Paddle; 1] = [Paddle{
+        rect: Rect{left: 776.0, top: 280.0, right: 784.0, bottom: 320.0},
+        vel: 0.0,
+        side: Side::Right,
+    }];
+
+    fn frame<'a>(pads: &'a Gamepads, view: &'a Viewport, ball: Position, ball_vel: Velocity) -> Frame<'a> {
+        Frame{
+            ctx: None, pads, view,
+            remote: None, remote_serve: false,
+            side: Side::Left,
+            // long enough that the nudge isn't cut short
+            dt: 1.0,
+            paddle: Position{x: 20.0, y: 300.0},
+            height: 40.0,
+            ball, ball_vel,
+            paddles: &OPPONENT,
+            scores: [0; 4],
+        }
+    }
+
+    #[test]
+    fn nudges_to_the_intercept() {
+        let physics = PhysicsConfig::default();
+        let mut assist = AssistInput::new(Box::new(Still), 1.0, &physics);
+        let (pads, view) = (Gamepads::default(), Viewport::unscaled());
+        // coming in steeply enough that a computer would meet it off
+        // centre to send it to the far corner
+        let face = 20.0 + physics.paddle_width / 2.0 + physics.ball_radius;
+        let ball = Position{x: 420.0, y: 200.0};
+        let vel = Velocity{x: -400.0, y: -75.0};
+        let intercept = 200.0 - (ball.x - face) * 75.0 / 400.0;
+        match assist.poll(&frame(&pads, &view, ball, vel)) {
+            Command::Move(d) => assert!((300.0 + d - intercept).abs() < 0.01, "moved to {}, not {}", 300.0 + d, intercept),
+            command => panic!("{:?}", command),
+        }
+    }
+
+    #[test]
+    fn leaves_the_paddle_alone_when_the_ball_goes_away() {
+        let physics = PhysicsConfig::default();
+        let mut assist = AssistInput::new(Box::new(Still), 1.0, &physics);
+        let (pads, view) = (Gamepads::default(), Viewport::unscaled());
+        let command = assist.poll(&frame(&pads, &view, Position{x: 420.0, y: 200.0}, Velocity{x: 400.0, y: 75.0}));
+        assert_eq!(command, Command::Move(0.0));
+    }
+}
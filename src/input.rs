@@ -0,0 +1,475 @@
+use ggez::Context;
+use ggez::event::{Axis, Button, KeyCode};
+use ggez::input::{keyboard, mouse};
+use ggez::input::mouse::MouseButton;
+use ggez::input::gamepad::GamepadId;
+use structopt::StructOpt;
+
+use crate::{Position, Velocity};
+use crate::collision::{Paddle, Side};
+use crate::flexcontrol::{FlexControl, Calibration, SerialRead};
+use crate::view::Viewport;
+
+// full keyboard paddle speed in pixels per second
+pub const KEY_SPEED: f32 = 480.0;
+// full gamepad stick deflection in pixels per second
+const STICK_SPEED: f32 = 720.0;
+// pixels per FlexControl count at unit response
+const FLEX_GAIN: f32 = 3.0;
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum Control {
+    Keyboard,
+    Mouse,
+    Gamepad(usize),
+    Flex(String),
+    FlexAbsolute(String),
+}
+
+// Shapes raw analog input. The exponent is shared by gamepad sticks and
+// FlexControl counts; the deadzone only applies to sticks (-1.0..=1.0).
+#[derive(Clone, Copy, Debug)]
+pub struct ResponseCurve {
+    pub deadzone: f32,
+    pub exponent: f32,
+}
+
+impl ResponseCurve {
+    pub fn stick(&self, x: f32) -> f32 {
+        let magnitude = (x.abs() - self.deadzone) / (1.0 - self.deadzone);
+        if magnitude <= 0.0 {
+            return 0.0;
+        }
+        x.signum() * magnitude.min(1.0).powf(self.exponent)
+    }
+
+    pub fn flex(&self, count: i32) -> f32 {
+        let x = count as f32;
+        FLEX_GAIN * x.signum() * x.abs().powf(self.exponent)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct InputConfig {
+    pub sensitivity: f32,
+    pub acceleration: f32,
+    pub curve: ResponseCurve,
+    // ignore saved FlexControl calibrations and capture new ones
+    pub calibrate: bool,
+}
+
+// the command line options behind an InputConfig, shared by the game and
+// practice
+#[derive(StructOpt, Debug)]
+pub struct InputOpt {
+    /// Paddle speed multiplier for keyboard and mouse players
+    #[structopt(long, default_value = "1.0")]
+    sensitivity: f32,
+    /// How quickly held keys reach full paddle speed (0 for instant)
+    #[structopt(long, default_value = "8.0")]
+    acceleration: f32,
+    /// Gamepad stick deadzone as a fraction of full deflection, below 1
+    #[structopt(long, default_value = "0.15", parse(try_from_str = parse_deadzone))]
+    deadzone: f32,
+    /// Response curve exponent for gamepad sticks and FlexControl knobs
+    #[structopt(long, default_value = "2.0")]
+    curve: f32,
+    /// Recapture FlexControl knob stops instead of using saved calibrations
+    #[structopt(long)]
+    calibrate: bool,
+}
+
+// a deadzone of the whole stick would leave nothing to scale the rest into
+fn parse_deadzone(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(deadzone) if (0.0..1.0).contains(&deadzone) => Ok(deadzone),
+        Ok(_) => Err(format!("deadzone {} must be at least 0 and below 1", s)),
+        Err(_) => Err(format!("bad deadzone '{}'", s)),
+    }
+}
+
+impl InputOpt {
+    pub fn config(&self) -> InputConfig {
+        InputConfig{
+            sensitivity: self.sensitivity,
+            acceleration: self.acceleration,
+            curve: ResponseCurve{deadzone: self.deadzone, exponent: self.curve},
+            calibrate: self.calibrate,
+        }
+    }
+}
+
+// Input state gathered by MainState from events and the network.
+pub struct Devices<'a> {
+    pub pads:   &'a Gamepads,
+    pub view:   &'a Viewport,
+    // latest paddle position and serve button from the network, by slot
+    pub remote: [Option<f32>; 4],
+    pub remote_serve: [bool; 4],
+    // every paddle on the field
+    pub paddles: &'a [Paddle],
+    // points, by slot
+    pub scores: [i32; 4],
+}
+
+// What a controller gets to see each tick. Positions a controller hands
+// back are along the paddle's wall: y for left and right, x for top and
+// bottom.
+pub struct Frame<'a> {
+    // none without a window
+    pub ctx:      Option<&'a Context>,
+    pub pads:     &'a Gamepads,
+    pub view:     &'a Viewport,
+    pub remote:   Option<f32>,
+    pub remote_serve: bool,
+    pub side:     Side,
+    pub dt:       f32,
+    pub paddle:   Position,
+    pub height:   f32,
+    pub ball:     Position,
+    pub ball_vel: Velocity,
+    pub paddles:  &'a [Paddle],
+    pub scores:   [i32; 4],
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Command {
+    Hold,
+    // relative movement in pixels for this tick
+    Move(f32),
+    // absolute paddle centre
+    Goto(f32),
+}
+
+pub trait PaddleInput {
+    fn poll(&mut self, frame: &Frame) -> Command;
+
+    // true while the player is asking to serve
+    fn serve(&mut self, _frame: &Frame) -> bool {
+        false
+    }
+
+    // controllers that need a setup step before play report it here
+    fn calibrating(&self) -> bool {
+        false
+    }
+
+    fn finish_calibration(&mut self) {}
+
+    // controllers that learn about their opponent start over here
+    fn new_match(&mut self) {}
+
+    // serial controllers hand over timing of their latest read
+    fn take_serial_read(&mut self) -> Option<SerialRead> {
+        None
+    }
+}
+
+pub struct KeyboardInput {
+    side: Side,
+    config: InputConfig,
+    vel: f32,
+}
+
+impl KeyboardInput {
+    pub fn new(side: Side, config: InputConfig) -> Self {
+        KeyboardInput{side, config, vel: 0.0}
+    }
+}
+
+impl PaddleInput for KeyboardInput {
+    fn poll(&mut self, frame: &Frame) -> Command {
+        let direction = match frame.ctx {
+            Some(ctx) => key_direction(ctx, self.side),
+            None => 0.0,
+        };
+        self.vel = key_velocity(self.vel, direction, frame.dt, &self.config);
+        Command::Move(self.vel * frame.dt)
+    }
+
+    fn serve(&mut self, frame: &Frame) -> bool {
+        matches!(frame.ctx, Some(ctx) if keyboard::is_key_pressed(ctx, serve_key(self.side)))
+    }
+}
+
+pub struct MouseInput {
+    config: InputConfig,
+}
+
+impl MouseInput {
+    pub fn new(config: InputConfig) -> Self {
+        MouseInput{config}
+    }
+}
+
+impl PaddleInput for MouseInput {
+    fn poll(&mut self, frame: &Frame) -> Command {
+        let ctx = match frame.ctx {
+            Some(ctx) => ctx,
+            None => return Command::Hold,
+        };
+        // mouse mapped around the middle of the wall, scaled by sensitivity
+        let mid = frame.side.span() / 2.0;
+        let along = frame.side.along(frame.view.to_logical(mouse::position(ctx)));
+        Command::Goto(mid + (along - mid) * self.config.sensitivity)
+    }
+
+    fn serve(&mut self, frame: &Frame) -> bool {
+        matches!(frame.ctx, Some(ctx) if mouse::button_pressed(ctx, MouseButton::Left))
+    }
+}
+
+pub struct GamepadInput {
+    index: usize,
+    config: InputConfig,
+}
+
+impl GamepadInput {
+    pub fn new(index: usize, config: InputConfig) -> Self {
+        GamepadInput{index, config}
+    }
+}
+
+impl PaddleInput for GamepadInput {
+    fn poll(&mut self, frame: &Frame) -> Command {
+        let horizontal = frame.side.horizontal();
+        Command::Move(frame.pads.velocity(self.index, horizontal, &self.config) * frame.dt)
+    }
+
+    fn serve(&mut self, frame: &Frame) -> bool {
+        frame.pads.serve(self.index)
+    }
+}
+
+pub struct FlexInput {
+    flex: FlexControl,
+    config: InputConfig,
+}
+
+impl FlexInput {
+    pub fn new(port: &str, config: InputConfig) -> Self {
+        FlexInput{flex: FlexControl::new(port), config}
+    }
+}
+
+impl PaddleInput for FlexInput {
+    fn poll(&mut self, _frame: &Frame) -> Command {
+        Command::Move(self.config.curve.flex(self.flex.read()) * self.config.sensitivity)
+    }
+
+    fn serve(&mut self, _frame: &Frame) -> bool {
+        self.flex.take_press()
+    }
+
+    fn take_serial_read(&mut self) -> Option<SerialRead> {
+        self.flex.take_last_read()
+    }
+}
+
+// Treats the knob as an absolute dial: the calibrated travel maps onto the
+// full paddle range. Turning past either stop drags the range along, so
+// the knob can never drift out of step with the paddle.
+// Every launch starts in setup. With no saved calibration the knob is
+// turned from stop to stop to measure its travel; with one, touching the
+// top stop is enough to find where the travel starts.
+pub struct AbsoluteFlexInput {
+    port: String,
+    flex: FlexControl,
+    count: i32,
+    // counts at the top and bottom of the paddle's travel
+    min: i32,
+    max: i32,
+    saved: Option<Calibration>,
+    calibrating: bool,
+}
+
+impl AbsoluteFlexInput {
+    pub fn new(port: &str, config: InputConfig) -> Self {
+        let saved = if config.calibrate {None} else {Calibration::load(port)};
+        AbsoluteFlexInput{
+            port: port.to_string(),
+            flex: FlexControl::new(port),
+            count: 0,
+            min: 0,
+            max: 0,
+            saved,
+            calibrating: true,
+        }
+    }
+}
+
+impl PaddleInput for AbsoluteFlexInput {
+    fn poll(&mut self, frame: &Frame) -> Command {
+        self.count += self.flex.read();
+        if self.calibrating {
+            self.min = self.min.min(self.count);
+            self.max = self.max.max(self.count);
+            return Command::Hold;
+        }
+        // turning past either end means the stops have moved; follow them
+        let span = self.max - self.min;
+        if self.count < self.min {
+            self.min = self.count;
+        } else if self.count > self.max {
+            self.min = self.count - span;
+        }
+        self.max = self.min + span;
+
+        let frac = (self.count - self.min) as f32 / span as f32;
+        Command::Goto(frame.height/2.0 + frac * (frame.side.span() - frame.height))
+    }
+
+    fn serve(&mut self, _frame: &Frame) -> bool {
+        self.flex.take_press()
+    }
+
+    fn calibrating(&self) -> bool {
+        self.calibrating
+    }
+
+    // the lowest count seen during setup is the top stop
+    fn finish_calibration(&mut self) {
+        if !self.calibrating {
+            return;
+        }
+        self.calibrating = false;
+        match self.saved {
+            Some(cal) => self.max = self.min + cal.span,
+            None => {
+                self.max = self.max.max(self.min + 1);
+                Calibration{span: self.max - self.min}.save(&self.port);
+            },
+        }
+    }
+
+    fn take_serial_read(&mut self) -> Option<SerialRead> {
+        self.flex.take_last_read()
+    }
+}
+
+// the (up or left, down or right) keys for a side
+pub fn keys(side: Side) -> (KeyCode, KeyCode) {
+    match side {
+        Side::Left   => (KeyCode::W, KeyCode::S),
+        Side::Right  => (KeyCode::Up, KeyCode::Down),
+        Side::Top    => (KeyCode::A, KeyCode::D),
+        Side::Bottom => (KeyCode::Left, KeyCode::Right),
+    }
+}
+
+pub fn serve_key(side: Side) -> KeyCode {
+    match side {
+        Side::Left   => KeyCode::E,
+        Side::Right  => KeyCode::Return,
+        Side::Top    => KeyCode::Q,
+        Side::Bottom => KeyCode::RShift,
+    }
+}
+
+// -1.0 for up or left, 1.0 for down or right, 0.0 when neither (or both)
+// keys are held
+pub fn key_direction(ctx: &Context, side: Side) -> f32 {
+    let (up, down) = keys(side);
+    match (keyboard::is_key_pressed(ctx, up), keyboard::is_key_pressed(ctx, down)) {
+        (true, false) => -1.0,
+        (false, true) =>  1.0,
+        _ => 0.0,
+    }
+}
+
+// ramp the current keyboard velocity toward the held direction
+// A move asked for by a program rather than a person. Bots get no faster
+// paddle than someone on the keyboard, so a match against one is won on
+// where it goes, not on how quickly.
+pub fn bot_move(d: f32, dt: f32) -> Command {
+    let limit = KEY_SPEED * dt;
+    Command::Move(d.max(-limit).min(limit))
+}
+
+pub fn key_velocity(current: f32, direction: f32, dt: f32, config: &InputConfig) -> f32 {
+    let target = direction * KEY_SPEED * config.sensitivity;
+    if direction == 0.0 || config.acceleration <= 0.0 {
+        return target;
+    }
+    let step = KEY_SPEED * config.sensitivity * config.acceleration * dt;
+    if current.signum() != direction.signum() {
+        // reversing starts from rest rather than decelerating through zero
+        return direction * step.min(target.abs());
+    }
+    let speed = (current.abs() + step).min(target.abs());
+    direction * speed
+}
+
+#[derive(Default, Clone, Copy)]
+struct PadState {
+    stick:   f32,
+    stick_x: f32,
+    up:      bool,
+    down:    bool,
+    left:    bool,
+    right:   bool,
+    serve:   bool,
+}
+
+// Gamepads are numbered in the order they first send an event, which is
+// what `pad:0`, `pad:1` refer to.
+#[derive(Default)]
+pub struct Gamepads {
+    pads: Vec<(GamepadId, PadState)>,
+}
+
+impl Gamepads {
+    fn slot(&mut self, id: GamepadId) -> &mut PadState {
+        let index = match self.pads.iter().position(|(pad, _)| *pad == id) {
+            Some(index) => index,
+            None => {
+                self.pads.push((id, PadState::default()));
+                self.pads.len() - 1
+            }
+        };
+        &mut self.pads[index].1
+    }
+
+    pub fn axis(&mut self, axis: Axis, value: f32, id: GamepadId) {
+        match axis {
+            Axis::LeftStickY => self.slot(id).stick = value,
+            Axis::LeftStickX => self.slot(id).stick_x = value,
+            _ => (),
+        }
+    }
+
+    pub fn button(&mut self, btn: Button, pressed: bool, id: GamepadId) {
+        let pad = self.slot(id);
+        match btn {
+            Button::DPadUp   => pad.up = pressed,
+            Button::DPadDown => pad.down = pressed,
+            Button::DPadLeft  => pad.left = pressed,
+            Button::DPadRight => pad.right = pressed,
+            Button::South => pad.serve = pressed,
+            _ => (),
+        }
+    }
+
+    pub fn serve(&self, index: usize) -> bool {
+        matches!(self.pads.get(index), Some((_, pad)) if pad.serve)
+    }
+
+    // paddle velocity in pixels per second for the given pad, read off the
+    // stick's x axis and the d-pad's left and right for horizontal paddles
+    pub fn velocity(&self, index: usize, horizontal: bool, config: &InputConfig) -> f32 {
+        let pad = match self.pads.get(index) {
+            Some((_, pad)) => pad,
+            None => return 0.0,
+        };
+        let (back, forward) = if horizontal {(pad.left, pad.right)} else {(pad.up, pad.down)};
+        let dpad = match (back, forward) {
+            (true, false) => -1.0,
+            (false, true) =>  1.0,
+            _ => 0.0,
+        };
+        // stick up is positive, screen up is negative
+        let stick = if horizontal {config.curve.stick(pad.stick_x)} else {-config.curve.stick(pad.stick)};
+        let direction = if dpad != 0.0 {dpad} else {stick};
+        direction * STICK_SPEED * config.sensitivity
+    }
+}
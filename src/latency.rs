@@ -0,0 +1,71 @@
+use std::time::{Duration, Instant};
+
+use crate::flexcontrol::SerialRead;
+
+// how often the running numbers get printed
+const REPORT_EVERY: Duration = Duration::from_secs(5);
+
+// Input-to-photon and serial read latency, measured as best we can from
+// inside the game: an input is stamped when we first see it and counted as
+// shown once the next frame has been presented.
+pub struct LatencyProbe {
+    pending: Vec<Instant>,
+    photon: Vec<Duration>,
+    serial: Vec<Duration>,
+    last_report: Instant,
+}
+
+fn percentile(sorted: &[Duration], p: f32) -> Duration {
+    if sorted.is_empty() {
+        return Duration::from_secs(0);
+    }
+    let index = ((sorted.len() - 1) as f32 * p).round() as usize;
+    sorted[index]
+}
+
+fn summary(name: &str, samples: &[Duration]) -> String {
+    let mut sorted = samples.to_vec();
+    sorted.sort();
+    let ms = |d: Duration| d.as_secs_f32() * 1000.0;
+    format!("{:<8} n={:<6} p50={:6.2}ms p90={:6.2}ms p99={:6.2}ms max={:6.2}ms",
+        name,
+        sorted.len(),
+        ms(percentile(&sorted, 0.5)),
+        ms(percentile(&sorted, 0.9)),
+        ms(percentile(&sorted, 0.99)),
+        ms(percentile(&sorted, 1.0)),
+    )
+}
+
+impl LatencyProbe {
+    pub fn start() -> LatencyProbe {
+        LatencyProbe{pending: vec![], photon: vec![], serial: vec![], last_report: Instant::now()}
+    }
+
+    pub fn input(&mut self, at: Instant) {
+        self.pending.push(at);
+    }
+
+    pub fn serial(&mut self, read: SerialRead) {
+        self.serial.push(read.took);
+        if read.counts != 0 {
+            self.input(read.started);
+        }
+    }
+
+    // call straight after graphics::present
+    pub fn presented(&mut self) {
+        let now = Instant::now();
+        for at in self.pending.drain(..) {
+            self.photon.push(now - at);
+        }
+        if now - self.last_report >= REPORT_EVERY {
+            self.last_report = now;
+            println!("{}", self.report());
+        }
+    }
+
+    pub fn report(&self) -> String {
+        format!("{}\n{}", summary("photon", &self.photon), summary("serial", &self.serial))
+    }
+}
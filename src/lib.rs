@@ -0,0 +1,139 @@
+mod ai;
+mod arena;
+mod attract;
+mod ball;
+mod bot;
+mod collision;
+mod player;
+mod game;
+mod flexcontrol;
+mod gym;
+mod handicap;
+mod input;
+mod latency;
+mod netpong;
+mod network;
+mod physics;
+mod powerup;
+mod practice;
+mod random;
+mod replay;
+mod rules;
+mod scoring;
+mod script;
+mod sim;
+mod tournament;
+mod view;
+mod wacky_tube_man;
+
+// the learning environment and what it takes to set one up, for training
+// code that links against netpong rather than talking to `netpong gym`
+pub use ai::Level;
+pub use gym::{Env, Observation};
+pub use physics::PhysicsConfig;
+pub use player::PlayerType;
+
+use collision::Side;
+use game::{MainState, Opt};
+use gym::GymOpt;
+use handicap::{AssistInput, Handicap};
+use input::{Control, InputConfig};
+use player::Player;
+use practice::{Practice, PracticeOpt};
+use replay::Recorder;
+use tournament::TournamentOpt;
+
+use structopt::StructOpt;
+
+use ggez::{graphics, Context, GameResult};
+use ggez::mint as na;
+
+pub type Position = na::Point2<f32>;
+pub type Velocity = na::Vector2<f32>;
+
+// logical playfield size; the window is scaled to fit by Viewport
+pub const SCREEN_WIDTH:  f32 = 800.0;
+pub const SCREEN_HEIGHT: f32 = 600.0;
+
+// every player argument goes through check_player first, so this only
+// panics on names that didn't come from the command line
+fn to_player_type(s: &str) -> PlayerType {
+    player_type(s).unwrap_or_else(|err| panic!("{}", err))
+}
+
+// for structopt's validator
+fn check_player(s: String) -> Result<(), String> {
+    player_type(&s).map(|_| ())
+}
+
+// a player as named on the command line, e.g. computer:hard or bot:./follow.py
+pub fn player_type(s: &str) -> Result<PlayerType, String> {
+    match s.split_once(':') {
+        Some(("pad", index)) => index.parse().map(|index| PlayerType::Human(Control::Gamepad(index)))
+            .map_err(|_| format!("bad gamepad number in '{}', try pad:0", s)),
+        Some(("flex", port)) => Ok(PlayerType::Human(Control::Flex(port.to_string()))),
+        Some(("knob", port)) => Ok(PlayerType::Human(Control::FlexAbsolute(port.to_string()))),
+        Some(("replay", path)) => Ok(PlayerType::Replay(path.to_string())),
+        Some(("script", path)) => Ok(PlayerType::Script(path.to_string())),
+        Some(("bot", command)) => Ok(PlayerType::Bot(command.to_string())),
+        Some(("computer", level)) => level.parse().map(PlayerType::Computer),
+        _ => match s {
+            "network"   => Ok(PlayerType::Network(None)),
+            "computer"  => Ok(PlayerType::Computer(Level::default())),
+            "man"       => Ok(PlayerType::Human(Control::Keyboard)),
+            "keys"      => Ok(PlayerType::Human(Control::Keyboard)),
+            "mouse"     => Ok(PlayerType::Human(Control::Mouse)),
+            "pad"       => Ok(PlayerType::Human(Control::Gamepad(0))),
+            _           => Ok(PlayerType::Network(Some(s.to_string()))),
+        },
+    }
+}
+
+fn message(ctx: &mut Context, s: &str) -> GameResult {
+    let text = graphics::Text::new(s);
+    let r = text.dimensions(ctx);
+
+    graphics::draw(ctx, 
+        &text, 
+        graphics::DrawParam::default().dest([(SCREEN_WIDTH - r.w)/2.0, (SCREEN_HEIGHT - r.h)/2.0])
+    )?;
+    // graphics::present(ctx)?;
+    Ok(())
+}
+
+fn make_player(side: Side, me: &PlayerType, config: InputConfig, record: &Option<String>, handicap: &Handicap, physics: &PhysicsConfig) -> Player {
+    let mut input = me.input(side, config, physics);
+    if handicap.assist > 0.0 && me.is_human() {
+        input = Box::new(AssistInput::new(input, handicap.assist, physics));
+    }
+    if let Some(prefix) = record {
+        input = Box::new(Recorder::new(input, &format!("{}.{}", prefix, side.name())));
+    }
+    let mut player = Player::new(side, me, input, physics);
+    player.set_base_scale(handicap.height);
+    player
+}
+
+// the netpong command line
+pub fn run() -> GameResult {
+    // `netpong tournament ...` plays bots against each other and `netpong
+    // gym ...` serves a learning environment, both with no window; `netpong
+    // practice ...` is one player against a wall or a ball cannon
+    match std::env::args().nth(1).as_deref() {
+        Some("tournament") => {
+            tournament::run(TournamentOpt::from_iter(std::env::args().skip(1)));
+            return Ok(());
+        },
+        Some("gym") => {
+            gym::serve(GymOpt::from_iter(std::env::args().skip(1)));
+            return Ok(());
+        },
+        Some("practice") => {
+            let opt = PracticeOpt::from_iter(std::env::args().skip(1));
+            return game::window(|ctx| Practice::new(ctx, opt));
+        },
+        _ => (),
+    }
+    let opt = Opt::from_args();
+    game::window(|ctx| MainState::new(ctx, opt))
+}
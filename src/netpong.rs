@@ -16,6 +16,8 @@ use super::*;
 #[derive(Debug, Default, PartialEq, Clone)]
 pub struct Paddle {
     pub y: f32,
+    pub slot: u32,
+    pub serve: bool,
 }
 
 impl<'a> MessageRead<'a> for Paddle {
@@ -24,6 +26,8 @@ impl<'a> MessageRead<'a> for Paddle {
         while !r.is_eof() {
             match r.next_tag(bytes) {
                 Ok(13) => msg.y = r.read_float(bytes)?,
+                Ok(16) => msg.slot = r.read_uint32(bytes)?,
+                Ok(24) => msg.serve = r.read_bool(bytes)?,
                 Ok(t) => { r.read_unknown(bytes, t)?; }
                 Err(e) => return Err(e),
             }
@@ -36,10 +40,14 @@ impl MessageWrite for Paddle {
     fn get_size(&self) -> usize {
         0
         + if self.y == 0f32 { 0 } else { 1 + 4 }
+        + if self.slot == 0u32 { 0 } else { 1 + sizeof_varint(*(&self.slot) as u64) }
+        + if self.serve == false { 0 } else { 1 + sizeof_varint(*(&self.serve) as u64) }
     }
 
     fn write_message<W: WriterBackend>(&self, w: &mut Writer<W>) -> Result<()> {
         if self.y != 0f32 { w.write_with_tag(13, |w| w.write_float(*&self.y))?; }
+        if self.slot != 0u32 { w.write_with_tag(16, |w| w.write_uint32(*&self.slot))?; }
+        if self.serve != false { w.write_with_tag(24, |w| w.write_bool(*&self.serve))?; }
         Ok(())
     }
 }
@@ -50,6 +58,7 @@ pub struct Ball {
     pub y: f32,
     pub vx: f32,
     pub vy: f32,
+    pub id: u32,
 }
 
 impl<'a> MessageRead<'a> for Ball {
@@ -61,6 +70,7 @@ impl<'a> MessageRead<'a> for Ball {
                 Ok(21) => msg.y = r.read_float(bytes)?,
                 Ok(29) => msg.vx = r.read_float(bytes)?,
                 Ok(37) => msg.vy = r.read_float(bytes)?,
+                Ok(40) => msg.id = r.read_uint32(bytes)?,
                 Ok(t) => { r.read_unknown(bytes, t)?; }
                 Err(e) => return Err(e),
             }
@@ -76,6 +86,7 @@ impl MessageWrite for Ball {
         + if self.y == 0f32 { 0 } else { 1 + 4 }
         + if self.vx == 0f32 { 0 } else { 1 + 4 }
         + if self.vy == 0f32 { 0 } else { 1 + 4 }
+        + if self.id == 0u32 { 0 } else { 1 + sizeof_varint(*(&self.id) as u64) }
     }
 
     fn write_message<W: WriterBackend>(&self, w: &mut Writer<W>) -> Result<()> {
@@ -83,6 +94,101 @@ impl MessageWrite for Ball {
         if self.y != 0f32 { w.write_with_tag(21, |w| w.write_float(*&self.y))?; }
         if self.vx != 0f32 { w.write_with_tag(29, |w| w.write_float(*&self.vx))?; }
         if self.vy != 0f32 { w.write_with_tag(37, |w| w.write_float(*&self.vy))?; }
+        if self.id != 0u32 { w.write_with_tag(40, |w| w.write_uint32(*&self.id))?; }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct PowerUp {
+    pub id: u32,
+    pub kind: u32,
+    pub x: f32,
+    pub y: f32,
+    pub holder: u32,
+    pub remaining: f32,
+    pub ball: u32,
+}
+
+impl<'a> MessageRead<'a> for PowerUp {
+    fn from_reader(r: &mut BytesReader, bytes: &'a [u8]) -> Result<Self> {
+        let mut msg = Self::default();
+        while !r.is_eof() {
+            match r.next_tag(bytes) {
+                Ok(8) => msg.id = r.read_uint32(bytes)?,
+                Ok(16) => msg.kind = r.read_uint32(bytes)?,
+                Ok(29) => msg.x = r.read_float(bytes)?,
+                Ok(37) => msg.y = r.read_float(bytes)?,
+                Ok(40) => msg.holder = r.read_uint32(bytes)?,
+                Ok(53) => msg.remaining = r.read_float(bytes)?,
+                Ok(56) => msg.ball = r.read_uint32(bytes)?,
+                Ok(t) => { r.read_unknown(bytes, t)?; }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(msg)
+    }
+}
+
+impl MessageWrite for PowerUp {
+    fn get_size(&self) -> usize {
+        0
+        + if self.id == 0u32 { 0 } else { 1 + sizeof_varint(*(&self.id) as u64) }
+        + if self.kind == 0u32 { 0 } else { 1 + sizeof_varint(*(&self.kind) as u64) }
+        + if self.x == 0f32 { 0 } else { 1 + 4 }
+        + if self.y == 0f32 { 0 } else { 1 + 4 }
+        + if self.holder == 0u32 { 0 } else { 1 + sizeof_varint(*(&self.holder) as u64) }
+        + if self.remaining == 0f32 { 0 } else { 1 + 4 }
+        + if self.ball == 0u32 { 0 } else { 1 + sizeof_varint(*(&self.ball) as u64) }
+    }
+
+    fn write_message<W: WriterBackend>(&self, w: &mut Writer<W>) -> Result<()> {
+        if self.id != 0u32 { w.write_with_tag(8, |w| w.write_uint32(*&self.id))?; }
+        if self.kind != 0u32 { w.write_with_tag(16, |w| w.write_uint32(*&self.kind))?; }
+        if self.x != 0f32 { w.write_with_tag(29, |w| w.write_float(*&self.x))?; }
+        if self.y != 0f32 { w.write_with_tag(37, |w| w.write_float(*&self.y))?; }
+        if self.holder != 0u32 { w.write_with_tag(40, |w| w.write_uint32(*&self.holder))?; }
+        if self.remaining != 0f32 { w.write_with_tag(53, |w| w.write_float(*&self.remaining))?; }
+        if self.ball != 0u32 { w.write_with_tag(56, |w| w.write_uint32(*&self.ball))?; }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Goal {
+    pub slot: u32,
+    pub last_hit: u32,
+    pub ball: u32,
+}
+
+impl<'a> MessageRead<'a> for Goal {
+    fn from_reader(r: &mut BytesReader, bytes: &'a [u8]) -> Result<Self> {
+        let mut msg = Self::default();
+        while !r.is_eof() {
+            match r.next_tag(bytes) {
+                Ok(8) => msg.slot = r.read_uint32(bytes)?,
+                Ok(16) => msg.last_hit = r.read_uint32(bytes)?,
+                Ok(24) => msg.ball = r.read_uint32(bytes)?,
+                Ok(t) => { r.read_unknown(bytes, t)?; }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(msg)
+    }
+}
+
+impl MessageWrite for Goal {
+    fn get_size(&self) -> usize {
+        0
+        + if self.slot == 0u32 { 0 } else { 1 + sizeof_varint(*(&self.slot) as u64) }
+        + if self.last_hit == 0u32 { 0 } else { 1 + sizeof_varint(*(&self.last_hit) as u64) }
+        + if self.ball == 0u32 { 0 } else { 1 + sizeof_varint(*(&self.ball) as u64) }
+    }
+
+    fn write_message<W: WriterBackend>(&self, w: &mut Writer<W>) -> Result<()> {
+        if self.slot != 0u32 { w.write_with_tag(8, |w| w.write_uint32(*&self.slot))?; }
+        if self.last_hit != 0u32 { w.write_with_tag(16, |w| w.write_uint32(*&self.last_hit))?; }
+        if self.ball != 0u32 { w.write_with_tag(24, |w| w.write_uint32(*&self.ball))?; }
         Ok(())
     }
 }
@@ -99,6 +205,8 @@ impl<'a> MessageRead<'a> for Update {
             match r.next_tag(bytes) {
                 Ok(10) => msg.UpdateType = netpong::mod_Update::OneOfUpdateType::paddle(r.read_message::<netpong::Paddle>(bytes)?),
                 Ok(18) => msg.UpdateType = netpong::mod_Update::OneOfUpdateType::ball(r.read_message::<netpong::Ball>(bytes)?),
+                Ok(26) => msg.UpdateType = netpong::mod_Update::OneOfUpdateType::powerup(r.read_message::<netpong::PowerUp>(bytes)?),
+                Ok(34) => msg.UpdateType = netpong::mod_Update::OneOfUpdateType::goal(r.read_message::<netpong::Goal>(bytes)?),
                 Ok(t) => { r.read_unknown(bytes, t)?; }
                 Err(e) => return Err(e),
             }
@@ -113,12 +221,16 @@ impl MessageWrite for Update {
         + match self.UpdateType {
             netpong::mod_Update::OneOfUpdateType::paddle(ref m) => 1 + sizeof_len((m).get_size()),
             netpong::mod_Update::OneOfUpdateType::ball(ref m) => 1 + sizeof_len((m).get_size()),
+            netpong::mod_Update::OneOfUpdateType::powerup(ref m) => 1 + sizeof_len((m).get_size()),
+            netpong::mod_Update::OneOfUpdateType::goal(ref m) => 1 + sizeof_len((m).get_size()),
             netpong::mod_Update::OneOfUpdateType::None => 0,
     }    }
 
     fn write_message<W: WriterBackend>(&self, w: &mut Writer<W>) -> Result<()> {
         match self.UpdateType {            netpong::mod_Update::OneOfUpdateType::paddle(ref m) => { w.write_with_tag(10, |w| w.write_message(m))? },
             netpong::mod_Update::OneOfUpdateType::ball(ref m) => { w.write_with_tag(18, |w| w.write_message(m))? },
+            netpong::mod_Update::OneOfUpdateType::powerup(ref m) => { w.write_with_tag(26, |w| w.write_message(m))? },
+            netpong::mod_Update::OneOfUpdateType::goal(ref m) => { w.write_with_tag(34, |w| w.write_message(m))? },
             netpong::mod_Update::OneOfUpdateType::None => {},
     }        Ok(())
     }
@@ -132,6 +244,8 @@ use super::*;
 pub enum OneOfUpdateType {
     paddle(netpong::Paddle),
     ball(netpong::Ball),
+    powerup(netpong::PowerUp),
+    goal(netpong::Goal),
     None,
 }
 
@@ -0,0 +1,93 @@
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+
+use quick_protobuf::{BytesReader, Writer};
+
+use crate::netpong::Update;
+use crate::input::{PaddleInput, Frame, Command};
+
+pub const PORT: u16 = 34521;
+
+pub fn encode(message: &Update) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut writer = Writer::new(&mut out);
+    writer.write_message(message).expect("Cannot write message!");
+    out
+}
+
+pub fn decode(bytes: &[u8]) -> Option<Update> {
+    let mut reader = BytesReader::from_bytes(bytes);
+    reader.read_message::<Update>(bytes).ok()
+}
+
+// The connection to the other machines in the game. Every end listens on
+// PORT. An end started with an address talks only to that machine; one
+// started without learns its peers from the packets that arrive and passes
+// each packet on to the rest, so up to three machines can join it.
+pub struct Link {
+    socket: UdpSocket,
+    listening: bool,
+    remotes: Vec<SocketAddr>,
+}
+
+impl Link {
+    pub fn new(address: Option<&str>) -> Link {
+        let socket = UdpSocket::bind(("0.0.0.0", PORT)).expect("Can't bind UDP socket");
+        socket.set_nonblocking(true).expect("Can't make UDP socket non-blocking");
+        let remotes = address.iter().map(|address| {
+            (*address, PORT).to_socket_addrs().ok()
+                .and_then(|mut addrs| addrs.next())
+                .expect("Can't resolve network player address")
+        }).collect();
+        Link{socket, listening: address.is_none(), remotes}
+    }
+
+    pub fn connected(&self) -> bool {
+        !self.remotes.is_empty()
+    }
+
+    // the end everyone else connects to
+    pub fn listening(&self) -> bool {
+        self.listening
+    }
+
+    pub fn send(&self, message: &Update) {
+        let bytes = encode(message);
+        for remote in &self.remotes {
+            // dropped packets are fine, the next frame sends fresh state
+            let _ = self.socket.send_to(&bytes, remote);
+        }
+    }
+
+    // everything that has arrived since the last call
+    pub fn receive(&mut self) -> Vec<Update> {
+        let mut buf = [0u8; 512];
+        let mut updates = vec![];
+        while let Ok((n, from)) = self.socket.recv_from(&mut buf) {
+            if self.listening {
+                if !self.remotes.contains(&from) {
+                    self.remotes.push(from);
+                }
+                for remote in self.remotes.iter().filter(|remote| **remote != from) {
+                    let _ = self.socket.send_to(&buf[..n], remote);
+                }
+            }
+            if let Some(update) = decode(&buf[..n]) {
+                updates.push(update);
+            }
+        }
+        updates
+    }
+}
+
+// Follows the paddle position and serve button the link last received.
+pub struct NetworkInput;
+
+impl PaddleInput for NetworkInput {
+    fn poll(&mut self, frame: &Frame) -> Command {
+        frame.remote.map_or(Command::Hold, Command::Goto)
+    }
+
+    fn serve(&mut self, frame: &Frame) -> bool {
+        frame.remote_serve
+    }
+}
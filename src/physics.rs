@@ -0,0 +1,50 @@
+use std::fs;
+
+use serde::Deserialize;
+
+use crate::SCREEN_HEIGHT;
+
+// Everything that decides how the ball and paddles move. Defaults are the
+// classic game; any field left out of a physics file keeps its default.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default)]
+pub struct PhysicsConfig {
+    // serve speed along each axis, pixels per second
+    pub ball_speed: f32,
+    pub ball_radius: f32,
+    // ball speed multiplies by this every two consecutive hits
+    pub speed_up: f32,
+    // consecutive hits after which the ball stops getting faster
+    pub max_hits: i32,
+    // steepest rebound off a paddle, degrees from horizontal
+    pub max_angle: f32,
+    pub paddle_height: f32,
+    pub paddle_width: f32,
+    // distance from the paddle centre to its goal line
+    pub paddle_inset: f32,
+    // seconds the ball waits before each serve
+    pub serve_delay: f32,
+}
+
+impl Default for PhysicsConfig {
+    fn default() -> Self {
+        PhysicsConfig{
+            ball_speed: 360.0,
+            ball_radius: 6.0,
+            speed_up: 1.1,
+            max_hits: 44,
+            max_angle: 60.0,
+            paddle_height: SCREEN_HEIGHT / 15.0,
+            paddle_width: SCREEN_HEIGHT / 100.0,
+            paddle_inset: SCREEN_HEIGHT / 15.0,
+            serve_delay: 0.5,
+        }
+    }
+}
+
+impl PhysicsConfig {
+    pub fn load(path: &str) -> PhysicsConfig {
+        let text = fs::read_to_string(path).expect("Couldn't read physics file");
+        toml::from_str(&text).expect("Couldn't parse physics file")
+    }
+}
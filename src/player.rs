@@ -1,15 +1,27 @@
-use std::net::UdpSocket;
+use std::cell::Cell;
+use std::rc::Rc;
 
-use quick_protobuf::{Writer};
 use ggez::{Context, GameResult, graphics};
-use ggez::input::mouse;
 
 use crate::netpong::mod_Update::{OneOfUpdateType};
-use crate::netpong::{Update, Paddle};
+use crate::netpong::{Update, Paddle as PaddleMessage};
 
-use crate::{SCREEN_WIDTH, SCREEN_HEIGHT, Position, Ball};
+use crate::{SCREEN_WIDTH, SCREEN_HEIGHT, Position, Velocity};
+use crate::ball::Ball;
+use crate::input::{
+    Control, Command, Devices, Frame, InputConfig, PaddleInput,
+    KeyboardInput, MouseInput, GamepadInput, FlexInput, AbsoluteFlexInput,
+};
+use crate::ai::{ComputerInput, Level};
+use crate::network::NetworkInput;
+use crate::replay::ReplayInput;
+use crate::script::ScriptInput;
+use crate::bot::BotInput;
+use crate::gym::AgentInput;
+use crate::collision::{self, Paddle, Rect, Side};
+use crate::physics::PhysicsConfig;
+use crate::flexcontrol::SerialRead;
 use crate::wacky_tube_man::WackyTubeMan;
-use num_traits::float::FloatConst;
 
 pub enum PlayerMode {
     Active,
@@ -19,12 +31,19 @@ pub enum PlayerMode {
 
 pub struct Player {
     me: PlayerType,
+    input: Box<dyn PaddleInput>,
     pos: Position,
+    vel: f32,
     width: f32,
     height: f32,
-    is_left: bool,
-    last_ball: Option<Position>,
+    // height before power-ups
+    base_height: f32,
+    side: Side,
     pub score: i32,
+    // pressing serve this tick
+    serving: bool,
+    // goals let in; enough of them and the player is out
+    pub conceded: i32,
     t: f32,
     mode: PlayerMode,
     wacky: WackyTubeMan,
@@ -32,80 +51,209 @@ pub struct Player {
 
 #[derive(PartialEq, Clone)]
 pub enum PlayerType {
-    Human(String),
-    Computer,
+    Human(Control),
+    Computer(Level),
     Network(Option<String>),
+    Replay(String),
+    Script(String),
+    Bot(String),
+    // a learner in the gym, moved by the action it last chose
+    Agent(Rc<Cell<f32>>),
 }
 
-const PORT: u16 = 34521;
+impl PlayerType {
+    pub fn input(&self, side: Side, config: InputConfig, physics: &PhysicsConfig) -> Box<dyn PaddleInput> {
+        match self {
+            PlayerType::Human(Control::Keyboard)       => Box::new(KeyboardInput::new(side, config)),
+            PlayerType::Human(Control::Mouse)          => Box::new(MouseInput::new(config)),
+            PlayerType::Human(Control::Gamepad(index)) => Box::new(GamepadInput::new(*index, config)),
+            PlayerType::Human(Control::Flex(port))     => Box::new(FlexInput::new(port, config)),
+            PlayerType::Human(Control::FlexAbsolute(port)) => Box::new(AbsoluteFlexInput::new(port, config)),
+            PlayerType::Computer(level)                => Box::new(ComputerInput::new(*level, physics)),
+            PlayerType::Network(_)                     => Box::new(NetworkInput),
+            PlayerType::Replay(path)                   => Box::new(ReplayInput::new(path)),
+            PlayerType::Script(path)                   => Box::new(ScriptInput::new(path)),
+            PlayerType::Bot(command)                   => Box::new(BotInput::new(command)),
+            PlayerType::Agent(action)                  => Box::new(AgentInput::new(action.clone())),
+        }
+    }
+
+    pub fn is_local(&self) -> bool {
+        !matches!(self, PlayerType::Network(_))
+    }
+
+    pub fn is_human(&self) -> bool {
+        matches!(self, PlayerType::Human(_))
+    }
+}
+
+// paddle centre `inset` in from the wall on `side` and `along` it
+fn place(side: Side, inset: f32, along: f32) -> Position {
+    let mut pos = match side {
+        Side::Left   => Position{x: inset, y: 0.0},
+        Side::Right  => Position{x: SCREEN_WIDTH - inset, y: 0.0},
+        Side::Top    => Position{x: 0.0, y: inset},
+        Side::Bottom => Position{x: 0.0, y: SCREEN_HEIGHT - inset},
+    };
+    side.set_along(&mut pos, along);
+    pos
+}
 
 impl Player {
-    pub fn new(is_left: bool, me: &PlayerType) -> Self {
+    pub fn new(side: Side, me: &PlayerType, input: Box<dyn PaddleInput>, physics: &PhysicsConfig) -> Self {
         
-        let padding = SCREEN_HEIGHT / 15.0;
-        let x = if is_left {padding} else {SCREEN_WIDTH - padding};
-        let height = SCREEN_HEIGHT / 15.0;
-        let width  = SCREEN_HEIGHT / 100.0;
+        let pos = place(side, physics.paddle_inset, side.span()/30.0);
+        let height = physics.paddle_height;
+        let width  = physics.paddle_width;
         Player{
             me: me.clone(),
-            pos: Position{x, y: SCREEN_HEIGHT/30.0}, 
+            input,
+            pos,
+            vel: 0.0,
             width,
             height,
-            is_left, 
-            last_ball: None, 
+            base_height: height,
+            side,
             score: 0,
+            serving: false,
+            conceded: 0,
             t: 0.0,
             mode: PlayerMode::Active,
             wacky: WackyTubeMan::new(height, width, graphics::Color::from_rgb(255, 198, 41)),
         }
     }
 
+    // back to the start of a game
+    pub fn restart(&mut self) {
+        self.score = 0;
+        self.conceded = 0;
+        self.mode = PlayerMode::Active;
+    }
+
+    // change ends, keeping the same distance from the wall
+    pub fn set_side(&mut self, side: Side) {
+        let inset = self.side.depth(self.pos);
+        let along = self.along().min(side.span() - self.height/2.0);
+        self.pos = place(side, inset, along);
+        self.side = side;
+    }
+
     pub fn update_score(&mut self, increment: i32) -> i32 {
         self.score += increment;
         self.score
     }
 
-    pub fn update(&mut self, ctx: &mut Context, dt: f32, extra: f32) {
+    // `ctx` is none in a headless game, where only computer players and
+    // bots can play
+    pub fn update(&mut self, ctx: Option<&Context>, dt: f32, devices: &Devices, balls: &[Ball]) {
         self.t += dt;
         self.wacky.update(dt);
         self.wacky.set_position(self.pos.x, self.pos.y);
-        match self.me{
-            PlayerType::Human(_) => {
-                if self.is_left {
-                    //self.pos.y = mouse::position(ctx).y;
-                    self.pos.y += extra;
-                } else { 
-                    self.pos.y += extra;
-                }
-                self.pos.y = self.pos.y.max(self.height/2.0).min(SCREEN_HEIGHT - self.height/2.0);
-            },
-            _ => ()
+
+        let (ball, ball_vel) = match self.target(balls) {
+            Some(ball) => (ball.pos, ball.vel),
+            None => (Position{x: SCREEN_WIDTH/2.0, y: SCREEN_HEIGHT/2.0}, Velocity{x: 0.0, y: 0.0}),
+        };
+        let frame = Frame{
+            ctx, dt,
+            pads: devices.pads, view: devices.view,
+            remote: devices.remote[self.side as usize],
+            remote_serve: devices.remote_serve[self.side as usize],
+            side: self.side,
+            paddle: self.pos, height: self.height,
+            ball, ball_vel,
+            paddles: devices.paddles,
+            scores: devices.scores,
+        };
+        let before = self.along();
+        let along = match self.input.poll(&frame) {
+            Command::Hold    => before,
+            Command::Move(d) => before + d,
+            Command::Goto(a) => a,
+        };
+        self.serving = self.input.serve(&frame);
+        let along = along.max(self.height/2.0).min(self.side.span() - self.height/2.0);
+        self.side.set_along(&mut self.pos, along);
+        if dt > 0.0 {
+            self.vel = (along - before) / dt;
         }
+    }
+
+    pub fn serving(&self) -> bool {
+        self.serving
+    }
+
+    pub fn set_scale(&mut self, scale: f32) {
+        self.height = self.base_height * scale;
+    }
+
+    // a longer or shorter paddle for good, under any power-ups
+    pub fn set_base_scale(&mut self, scale: f32) {
+        self.base_height *= scale;
+        self.height = self.base_height;
+    }
+
+    pub fn side(&self) -> Side {
+        self.side
+    }
+
+    // paddle centre along its wall
+    pub fn along(&self) -> f32 {
+        self.side.along(self.pos)
+    }
+
+    pub fn is_local(&self) -> bool {
+        self.me.is_local()
+    }
+
+    pub fn is_human(&self) -> bool {
+        self.me.is_human()
+    }
+
+    // the ball that will reach this paddle soonest, or failing that the nearest
+    pub fn target<'a>(&self, balls: &'a [Ball]) -> Option<&'a Ball> {
+        let gap = |ball: &Ball| self.side.depth(ball.pos) - self.side.depth(self.pos);
+        let eta = |ball: &Ball| {
+            let t = gap(ball) / -collision::dot(ball.vel, self.side.normal());
+            if t >= 0.0 {t} else {f32::MAX}
+        };
+        let distance = |ball: &Ball| gap(ball).abs();
+        balls.iter().min_by(|a, b| {
+            eta(a).partial_cmp(&eta(b)).unwrap()
+                .then(distance(a).partial_cmp(&distance(b)).unwrap())
+        })
+    }
+
+    pub fn calibrating(&self) -> bool {
+        self.input.calibrating()
+    }
+
+    pub fn finish_calibration(&mut self) {
+        self.input.finish_calibration();
+    }
+
+    pub fn new_match(&mut self) {
+        self.input.new_match();
+    }
 
-        // match &self.opponent {
-        //     PlayerType::Network(Some(address)) => { 
-        //         // send network message with paddle position
-        //         let message = Update{UpdateType: OneOfUpdateType::paddle(Paddle{y:self.pos.y})};
-        //         let mut out = Vec::new();
-        //         let mut writer = Writer::new(&mut out);
-        //         writer.write_message(&message).expect("Cannot write message!");
-        //         if let Some(socket) = &self.out_sock {
-        //             socket.send_to(&out, format!("{}:{}", address, self.out_port)).expect("Can't send to UDP socket");
-        //         }
-        //     },
-        //     _ => ()
-        // }
+    pub fn take_serial_read(&mut self) -> Option<SerialRead> {
+        self.input.take_serial_read()
+    }
+
+    pub fn paddle_message(&self) -> Update {
+        Update{UpdateType: OneOfUpdateType::paddle(PaddleMessage{y: self.along(), slot: self.side as u32, serve: self.serving})}
     }
 
     pub fn draw(&self, ctx: &mut Context) -> GameResult {
+        let rect = self.paddle().rect;
         let mesh = graphics::Mesh::new_rectangle(
             ctx, 
             graphics::DrawMode::fill(),  
             graphics::Rect::new(
-                self.pos.x-self.width/2.0, 
-                self.pos.y-self.height/2.0, 
-                self.width, 
-                self.height), 
+                rect.left, 
+                rect.top, 
+                rect.right - rect.left, 
+                rect.bottom - rect.top), 
             graphics::Color::from_rgb(255, 198, 41))?;
 
         match self.mode {
@@ -131,50 +279,55 @@ impl Player {
         };
         let text = graphics::Text::new(fragment);
 
-        let score_x = if self.is_left {SCREEN_WIDTH * 0.25} else {SCREEN_WIDTH * 0.75};
-        let score_pos = [score_x, 40.0];
+        let score_pos = match self.side {
+            Side::Left   => [SCREEN_WIDTH * 0.25, 40.0],
+            Side::Right  => [SCREEN_WIDTH * 0.75, 40.0],
+            Side::Top    => [SCREEN_WIDTH * 0.5 + 20.0, SCREEN_HEIGHT * 0.25 - 32.0],
+            Side::Bottom => [SCREEN_WIDTH * 0.5 + 20.0, SCREEN_HEIGHT * 0.75 - 32.0],
+        };
         graphics::draw(ctx, &text, graphics::DrawParam::default().dest(score_pos))?;
         Ok(())
     }
 
-    pub fn check_for_hit(&mut self, ball: &mut Ball, ctx: &mut Context) { 
-        if self.me == PlayerType::Computer {
-            self.pos.y = ball.pos.y;
+    pub fn paddle(&self) -> Paddle {
+        let (width, height) = if self.side.horizontal() {
+            (self.height, self.width)
+        } else {
+            (self.width, self.height)
+        };
+        Paddle{
+            rect: Rect::centered(self.pos.x, self.pos.y, width, height),
+            vel: self.vel,
+            side: self.side,
         }
+    }
 
-        if let Some(prev) = &self.last_ball {
-
-            let (edge, possible) = if self.is_left {
-                let edge = self.pos.x + self.width/2.0 + ball.radius;
-                (edge, (ball.pos.x <= edge) && (self.pos.x <= prev.x))
-            } else {
-                let edge = self.pos.x - self.width/2.0 - ball.radius;
-                (edge, (ball.pos.x  >= edge) && (self.pos.x >= prev.x))
+    // demo mode: a perfect computer puts its paddle exactly where the first
+    // ball to arrive will cross its face during the coming step
+    pub fn anticipate(&mut self, balls: &[Ball], dt: f32) {
+        if self.me != PlayerType::Computer(Level::Perfect) {
+            return;
+        }
+        let face = self.side.depth(self.pos) + self.width/2.0;
+        let mut first: Option<(f32, f32)> = None;
+        for ball in balls {
+            let d = ball.displacement(dt);
+            let closing = -collision::dot(d, self.side.normal());
+            if closing == 0.0 {
+                continue;
+            }
+            let frac = (self.side.depth(ball.pos) - face - ball.radius) / closing;
+            let sooner = match first {
+                Some((best, _)) => frac < best,
+                None => true,
             };
-
-            if possible {
-                // find the time when the ball would have contacted the edge
-                let dx    = ball.pos.x - prev.x;
-                let dy    = ball.pos.y - prev.y;
-                let frac = (edge - prev.x)/dx;
-                let y    = prev.y + dy * frac;
-
-                if self.me == PlayerType::Computer {
-                    self.pos.y  = y; // demo mode
-                }
-                let top    = self.pos.y + self.height/2.0;
-                let bottom = self.pos.y - self.height/2.0;
-    
-                if (y >= bottom - ball.radius ) && (y <= top + ball.radius) {
-                    // collision!
-                    ball.paddle_strike(edge, y, self.pos.y, self.height, self.is_left, ctx);
-                    self.last_ball = None;
-                    return;
-                } 
+            if (0.0..=1.0).contains(&frac) && sooner {
+                let contact = Position{x: ball.pos.x + d.x * frac, y: ball.pos.y + d.y * frac};
+                first = Some((frac, self.side.along(contact)));
             }
         }
-        self.last_ball = Some(ball.pos);
+        if let Some((_, along)) = first {
+            self.side.set_along(&mut self.pos, along);
+        }
     }
-
-    
 }
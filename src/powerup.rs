@@ -0,0 +1,297 @@
+use ggez::{Context, GameResult, graphics};
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+use crate::{Position, SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::ball::Ball;
+use crate::collision::Side;
+use crate::netpong::PowerUp as PowerUpMessage;
+use crate::random;
+
+const RADIUS: f32 = 12.0;
+// most power-ups waiting on the field at once
+const MAX_ITEMS: usize = 2;
+// paddle height multipliers
+const GROW: f32 = 1.5;
+const SHRINK: f32 = 0.6;
+// ball speed multiplier while slowed
+const SLOW: f32 = 0.5;
+// seconds without hearing from the host before a power-up is forgotten
+const STALE: f32 = 0.5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    // the collector's paddle grows
+    Grow,
+    // everyone else's paddle shrinks
+    Shrink,
+    // the ball that collected it slows down
+    Slow,
+    // the ball that collected it disappears
+    Invisible,
+    // another ball is served
+    ExtraBall,
+}
+
+impl Kind {
+    // also the kind number sent over the network
+    pub const ALL: [Kind; 5] = [Kind::Grow, Kind::Shrink, Kind::Slow, Kind::Invisible, Kind::ExtraBall];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::Grow      => "grow",
+            Kind::Shrink    => "shrink",
+            Kind::Slow      => "slow",
+            Kind::Invisible => "invisible",
+            Kind::ExtraBall => "extra",
+        }
+    }
+
+    // seconds the effect lasts
+    fn duration(self) -> f32 {
+        match self {
+            Kind::Grow | Kind::Shrink => 10.0,
+            Kind::Slow      => 5.0,
+            Kind::Invisible => 2.0,
+            Kind::ExtraBall => 0.0,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Kind::Grow      => "+",
+            Kind::Shrink    => "-",
+            Kind::Slow      => "S",
+            Kind::Invisible => "?",
+            Kind::ExtraBall => "2",
+        }
+    }
+
+    fn colour(self) -> graphics::Color {
+        match self {
+            Kind::Grow      => graphics::Color::from_rgb(80, 200, 80),
+            Kind::Shrink    => graphics::Color::from_rgb(220, 60, 60),
+            Kind::Slow      => graphics::Color::from_rgb(80, 140, 255),
+            Kind::Invisible => graphics::Color::from_rgb(160, 160, 160),
+            Kind::ExtraBall => graphics::Color::from_rgb(255, 198, 41),
+        }
+    }
+}
+
+// Which kinds of power-up a match allows; empty turns them off.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Kinds(u8);
+
+impl Kinds {
+    // a comma separated list of kind names, or "all"
+    pub fn parse(list: &str) -> Kinds {
+        let mut kinds = Kinds::default();
+        for name in list.split(',').map(str::trim) {
+            for (i, kind) in Kind::ALL.iter().enumerate() {
+                if name == "all" || name == kind.name() {
+                    kinds.0 |= 1 << i;
+                }
+            }
+        }
+        kinds
+    }
+
+    fn list(self) -> Vec<Kind> {
+        Kind::ALL.iter().enumerate()
+            .filter(|(i, _)| self.0 & (1 << i) != 0)
+            .map(|(_, kind)| *kind)
+            .collect()
+    }
+}
+
+struct Item {
+    id: u32,
+    kind: Kind,
+    pos: Position,
+    // seconds since the host last mentioned it
+    age: f32,
+}
+
+struct Effect {
+    id: u32,
+    kind: Kind,
+    holder: Side,
+    ball: u32,
+    remaining: f32,
+    age: f32,
+}
+
+// Power-ups on the field and the effects of the ones collected. The host
+// spawns and collects them and sends the lot every frame; the other end
+// just shows what it was last told.
+pub struct PowerUps {
+    kinds: Kinds,
+    every: f32,
+    since_spawn: f32,
+    next_id: u32,
+    items: Vec<Item>,
+    effects: Vec<Effect>,
+}
+
+impl PowerUps {
+    pub fn new(kinds: Kinds, every: f32) -> Self {
+        PowerUps{kinds, every, since_spawn: 0.0, next_id: 1, items: vec![], effects: vec![]}
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+        self.effects.clear();
+    }
+
+    pub fn update(&mut self, dt: f32, host: bool) {
+        for effect in &mut self.effects {
+            effect.remaining -= dt;
+            effect.age += dt;
+        }
+        for item in &mut self.items {
+            item.age += dt;
+        }
+        if !host {
+            self.items.retain(|item| item.age < STALE);
+            self.effects.retain(|effect| effect.age < STALE);
+            return;
+        }
+        self.effects.retain(|effect| effect.remaining > 0.0);
+
+        let kinds = self.kinds.list();
+        self.since_spawn += dt;
+        if self.since_spawn < self.every || self.items.len() >= MAX_ITEMS {
+            return;
+        }
+        let spawn = random::with(|rng| kinds.choose(rng).map(|kind| (*kind, Position{
+            x: SCREEN_WIDTH * rng.gen_range(0.35, 0.65),
+            y: SCREEN_HEIGHT * rng.gen_range(0.15, 0.85),
+        })));
+        if let Some((kind, pos)) = spawn {
+            self.items.push(Item{id: self.next_id, kind, pos, age: 0.0});
+            self.next_id += 1;
+            self.since_spawn = 0.0;
+        }
+    }
+
+    // Balls pick up any power-up they run over for whoever hit them last.
+    // Returns how many extra balls were collected.
+    pub fn collect(&mut self, balls: &[Ball]) -> usize {
+        let mut extra = 0;
+        for ball in balls {
+            let holder = match ball.last_hit() {
+                Some(holder) => holder,
+                None => continue,
+            };
+            let reach = ball.radius + RADIUS;
+            let touching = |item: &Item| {
+                let (dx, dy) = (item.pos.x - ball.pos.x, item.pos.y - ball.pos.y);
+                dx * dx + dy * dy < reach * reach
+            };
+            for item in self.items.iter().filter(|item| touching(item)) {
+                if item.kind == Kind::ExtraBall {
+                    extra += 1;
+                    continue;
+                }
+                self.effects.push(Effect{
+                    id: item.id,
+                    kind: item.kind,
+                    holder,
+                    ball: ball.id,
+                    remaining: item.kind.duration(),
+                    age: 0.0,
+                });
+            }
+            self.items.retain(|item| !touching(item));
+        }
+        extra
+    }
+
+    // height multiplier for the paddle on a side
+    pub fn paddle_scale(&self, side: Side) -> f32 {
+        self.effects.iter().fold(1.0, |scale, effect| match effect.kind {
+            Kind::Grow if effect.holder == side => scale * GROW,
+            Kind::Shrink if effect.holder != side => scale * SHRINK,
+            _ => scale,
+        })
+    }
+
+    // speed multiplier for a ball
+    pub fn pace(&self, ball: u32) -> f32 {
+        let slowed = self.effects.iter().any(|effect| effect.kind == Kind::Slow && effect.ball == ball);
+        if slowed {SLOW} else {1.0}
+    }
+
+    pub fn hidden(&self, ball: u32) -> bool {
+        self.effects.iter().any(|effect| effect.kind == Kind::Invisible && effect.ball == ball)
+    }
+
+    pub fn to_messages(&self) -> Vec<PowerUpMessage> {
+        let items = self.items.iter().map(|item| PowerUpMessage{
+            id: item.id,
+            kind: item.kind as u32,
+            x: item.pos.x,
+            y: item.pos.y,
+            ..Default::default()
+        });
+        let effects = self.effects.iter().map(|effect| PowerUpMessage{
+            id: effect.id,
+            kind: effect.kind as u32,
+            holder: effect.holder as u32 + 1,
+            remaining: effect.remaining,
+            ball: effect.ball,
+            ..Default::default()
+        });
+        items.chain(effects).collect()
+    }
+
+    // take a power-up the host sent
+    pub fn apply(&mut self, message: &PowerUpMessage) {
+        let kind = match Kind::ALL.get(message.kind as usize) {
+            Some(kind) => *kind,
+            None => return,
+        };
+        self.items.retain(|item| item.id != message.id);
+        self.effects.retain(|effect| effect.id != message.id);
+        if message.holder == 0 {
+            let pos = Position{x: message.x, y: message.y};
+            self.items.push(Item{id: message.id, kind, pos, age: 0.0});
+        } else if let Some(holder) = Side::ALL.get(message.holder as usize - 1) {
+            self.effects.push(Effect{
+                id: message.id,
+                kind,
+                holder: *holder,
+                ball: message.ball,
+                remaining: message.remaining,
+                age: 0.0,
+            });
+        }
+    }
+
+    pub fn draw(&self, ctx: &mut Context) -> GameResult {
+        for item in &self.items {
+            let mesh = graphics::Mesh::new_circle(
+                ctx, graphics::DrawMode::stroke(2.0), item.pos, RADIUS, 0.1, item.kind.colour(),
+            )?;
+            graphics::draw(ctx, &mesh, graphics::DrawParam::default())?;
+            let text = graphics::Text::new(item.kind.label());
+            let r = text.dimensions(ctx);
+            graphics::draw(ctx, &text, graphics::DrawParam::default()
+                .dest([item.pos.x - r.w/2.0, item.pos.y - r.h/2.0])
+                .color(item.kind.colour()))?;
+        }
+
+        // running effects, listed along the bottom of the field
+        let mut x = 10.0;
+        for effect in &self.effects {
+            let label = format!("{} {} {:.0}", effect.holder.name(), effect.kind.name(), effect.remaining.ceil());
+            let text = graphics::Text::new(label);
+            let r = text.dimensions(ctx);
+            graphics::draw(ctx, &text, graphics::DrawParam::default()
+                .dest([x, SCREEN_HEIGHT - r.h - 10.0])
+                .color(effect.kind.colour()))?;
+            x += r.w + 20.0;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,449 @@
+use std::collections::BTreeMap;
+use std::f32::consts::{PI, SQRT_2};
+use std::fs;
+use std::str::FromStr;
+
+use ggez::event::{self, Axis, Button};
+use ggez::input::gamepad::GamepadId;
+use ggez::{graphics, Context, GameResult};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use structopt::StructOpt;
+
+use crate::{check_player, make_player, message, to_player_type, Position, Velocity, SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::arena::Arena;
+use crate::ball::{Ball, Sounds};
+use crate::collision::Side;
+use crate::handicap::Handicap;
+use crate::input::{Devices, Gamepads, InputOpt};
+use crate::physics::PhysicsConfig;
+use crate::player::Player;
+use crate::random;
+use crate::rules::Rules;
+use crate::view::Viewport;
+
+// how far the paddle has to move after a shot to count as reacting
+const REACT_MOVE: f32 = 4.0;
+
+// What the far end throws at the player. A wall sends back whatever it
+// gets; the cannon fires fresh balls: fast and flat, steep off the walls,
+// or alternating high and low.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Drill {
+    Wall,
+    Fast,
+    Steep,
+    Alternating,
+}
+
+impl Drill {
+    pub fn name(self) -> &'static str {
+        match self {
+            Drill::Wall        => "wall",
+            Drill::Fast        => "fast",
+            Drill::Steep       => "steep",
+            Drill::Alternating => "alternating",
+        }
+    }
+
+    // where the `n`th shot leaves the far end, at what angle from straight
+    // across (as a fraction of the steepest), and how fast (as a multiple of
+    // a serve)
+    fn shot(self, n: u32) -> (f32, f32, f32) {
+        let flip = if n % 2 == 1 {-1.0} else {1.0};
+        random::with(|rng| match self {
+            Drill::Wall        => (SCREEN_HEIGHT / 2.0, rng.gen_range(-0.5, 0.5), 1.0),
+            Drill::Fast        => (rng.gen_range(150.0, 450.0), rng.gen_range(-0.25, 0.25), 1.6),
+            Drill::Steep       => {
+                let sign = if rng.gen_bool(0.5) {1.0} else {-1.0};
+                (rng.gen_range(200.0, 400.0), sign * rng.gen_range(0.8, 1.0), 1.0)
+            },
+            Drill::Alternating => (SCREEN_HEIGHT / 2.0 - flip * 100.0, flip * 0.6, 1.2),
+        })
+    }
+}
+
+impl FromStr for Drill {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wall"        => Ok(Drill::Wall),
+            "fast"        => Ok(Drill::Fast),
+            "steep"       => Ok(Drill::Steep),
+            "alternating" => Ok(Drill::Alternating),
+            _ => Err(format!("unknown drill '{}', try wall, fast, steep or alternating", s)),
+        }
+    }
+}
+
+// How someone has done at one drill, over a session or all of them.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default)]
+pub struct DrillStats {
+    pub sessions: u32,
+    pub shots: u32,
+    pub hits: u32,
+    // seconds from each shot to the paddle first moving, summed
+    pub reaction: f32,
+    pub reactions: u32,
+    // returns reaching the top, middle and bottom thirds of the far end
+    pub placement: [u32; 3],
+    // best hit rate over a whole session
+    pub best: f32,
+}
+
+impl DrillStats {
+    pub fn hit_rate(&self) -> f32 {
+        if self.shots == 0 {0.0} else {self.hits as f32 / self.shots as f32}
+    }
+
+    pub fn mean_reaction(&self) -> Option<f32> {
+        if self.reactions == 0 {None} else {Some(self.reaction / self.reactions as f32)}
+    }
+
+    fn add(&mut self, session: &DrillStats) {
+        self.sessions += 1;
+        self.shots += session.shots;
+        self.hits += session.hits;
+        self.reaction += session.reaction;
+        self.reactions += session.reactions;
+        for (total, n) in self.placement.iter_mut().zip(session.placement) {
+            *total += n;
+        }
+        self.best = self.best.max(session.hit_rate());
+    }
+
+    fn describe(&self) -> String {
+        let reaction = match self.mean_reaction() {
+            Some(t) => format!("{:.2}s", t),
+            None => "-".to_string(),
+        };
+        let [top, middle, bottom] = self.placement;
+        format!("hits {}/{} ({:.0}%)   reaction {}\nreturns top {}  middle {}  bottom {}",
+            self.hits, self.shots, self.hit_rate() * 100.0, reaction, top, middle, bottom)
+    }
+}
+
+// Everything saved between practice sessions, by drill name.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Progress {
+    drills: BTreeMap<String, DrillStats>,
+}
+
+impl Progress {
+    pub fn load(path: &str) -> Progress {
+        match fs::read_to_string(path) {
+            Ok(text) => toml::from_str(&text).expect("Couldn't parse practice progress"),
+            Err(_) => Progress::default(),
+        }
+    }
+
+    pub fn save(&self, path: &str) {
+        let text = toml::to_string(self).expect("Couldn't write practice progress");
+        fs::write(path, text).expect("Couldn't save practice progress");
+    }
+}
+
+// netpong practice [options] [player]
+#[derive(StructOpt, Debug)]
+#[structopt(name = "practice")]
+pub struct PracticeOpt {
+    /// What to practise against: wall, fast, steep or alternating
+    #[structopt(long, default_value = "fast")]
+    drill: Drill,
+    /// Shots in a session
+    #[structopt(long, default_value = "20")]
+    shots: u32,
+    /// Seconds between cannon shots
+    #[structopt(long, default_value = "1.5")]
+    every: f32,
+    /// Shot speed multiplier
+    #[structopt(long, default_value = "1.0")]
+    speed: f32,
+    /// TOML file progress is kept in
+    #[structopt(long, default_value = "practice.toml")]
+    progress: String,
+    /// TOML file of physics parameters
+    #[structopt(long)]
+    physics: Option<String>,
+    #[structopt(flatten)]
+    input: InputOpt,
+    #[structopt(default_value = "man", validator = check_player)]
+    player: String,
+}
+
+// one shot on its way to the player
+struct Shot {
+    time: f32,
+    // where the paddle was when it was fired
+    from: f32,
+    reaction: Option<f32>,
+    hit: bool,
+}
+
+// One player on the left against a wall or a ball cannon on the right.
+pub struct Practice {
+    opt: PracticeOpt,
+    player: Player,
+    ball: Option<Ball>,
+    shot: Option<Shot>,
+    // seconds until the next shot is fired
+    wait: f32,
+    fired: u32,
+    session: DrillStats,
+    progress: Progress,
+    done: bool,
+    sounds: Sounds,
+    physics: PhysicsConfig,
+    arena: Arena,
+    pads: Gamepads,
+    view: Viewport,
+}
+
+impl Practice {
+    pub fn new(ctx: &mut Context, opt: PracticeOpt) -> GameResult<Self> {
+        let physics = match &opt.physics {
+            Some(path) => PhysicsConfig::load(path),
+            None => PhysicsConfig::default(),
+        };
+        let input = opt.input.config();
+        let me = to_player_type(&opt.player);
+        if !me.is_human() {
+            panic!("{} can't practise; try man, mouse, pad, flex:<port> or knob:<port>", opt.player);
+        }
+        let player = make_player(Side::Left, &me, input, &None, &Handicap::default(), &physics);
+        let (width, height) = graphics::drawable_size(ctx);
+        Ok(Practice{
+            wait: opt.every,
+            progress: Progress::load(&opt.progress),
+            opt,
+            player,
+            ball: None,
+            shot: None,
+            fired: 0,
+            session: DrillStats::default(),
+            done: false,
+            sounds: Sounds::new(ctx),
+            physics,
+            arena: Arena::load("classic"),
+            pads: Gamepads::default(),
+            view: Viewport::fit(ctx, width, height)?,
+        })
+    }
+
+    fn restart(&mut self) {
+        self.ball = None;
+        self.shot = None;
+        self.wait = self.opt.every;
+        self.fired = 0;
+        self.session = DrillStats::default();
+        self.done = false;
+    }
+
+    // the far end sends the next shot, or the wall sends the ball back
+    fn fire(&mut self, ctx: &mut Context) {
+        let (along, angle, speed) = self.opt.drill.shot(self.fired);
+        let angle = angle * self.physics.max_angle * PI / 180.0;
+        let speed = speed * self.opt.speed * self.physics.ball_speed * SQRT_2;
+        let mut ball = Ball::new(ctx, 0, &self.sounds, Rules::default(), self.physics);
+        ball.fire(
+            Position{x: SCREEN_WIDTH - self.physics.paddle_inset, y: along},
+            Velocity{x: -angle.cos() * speed, y: angle.sin() * speed},
+        );
+        self.ball = Some(ball);
+        self.start_shot();
+    }
+
+    fn start_shot(&mut self) {
+        self.fired += 1;
+        self.shot = Some(Shot{time: 0.0, from: self.player.along(), reaction: None, hit: false});
+    }
+
+    // The shot is over. A return that reached the far end at `far` is
+    // placed by which third of it it came to.
+    fn end_shot(&mut self, far: Option<f32>) {
+        let shot = match self.shot.take() {
+            Some(shot) => shot,
+            None => return,
+        };
+        self.session.shots += 1;
+        if let Some(t) = shot.reaction {
+            self.session.reaction += t;
+            self.session.reactions += 1;
+        }
+        if shot.hit {
+            self.session.hits += 1;
+            if let Some(along) = far {
+                let third = (along / SCREEN_HEIGHT * 3.0).max(0.0) as usize;
+                self.session.placement[third.min(2)] += 1;
+            }
+        }
+        if self.session.shots >= self.opt.shots {
+            self.finish();
+        }
+    }
+
+    fn finish(&mut self) {
+        self.ball = None;
+        self.done = true;
+        self.session.sessions = 1;
+        self.session.best = self.session.hit_rate();
+        self.progress.drills.entry(self.opt.drill.name().to_string())
+            .or_default()
+            .add(&self.session);
+        self.progress.save(&self.opt.progress);
+    }
+
+    fn play(&mut self, ctx: &mut Context, dt: f32) {
+        let goals = match self.opt.drill {
+            Drill::Wall => vec![Side::Left],
+            _ => vec![Side::Left, Side::Right],
+        };
+        let paddles = [self.player.paddle()];
+        let ball = match &mut self.ball {
+            Some(ball) => ball,
+            None => return,
+        };
+        let rally = ball.rally();
+        let (along, heading) = (ball.pos.y, ball.vel.x);
+        let goal = ball.update(dt, &paddles, &goals, &self.arena);
+        ball.play(ctx);
+        let hit = ball.rally() > rally;
+        let bounced = heading > 0.0 && ball.vel.x < 0.0;
+        let back = ball.pos.y;
+
+        if let Some(shot) = &mut self.shot {
+            shot.time += dt;
+            if shot.reaction.is_none() && (self.player.along() - shot.from).abs() > REACT_MOVE {
+                shot.reaction = Some(shot.time);
+            }
+            shot.hit |= hit;
+        }
+        match goal {
+            Some(Side::Left) => {
+                self.ball = None;
+                self.wait = self.opt.every;
+                self.end_shot(None);
+            },
+            Some(_) => {
+                self.ball = None;
+                self.wait = self.opt.every;
+                self.end_shot(Some(along));
+            },
+            // off the wall and straight back for another go
+            None if bounced && self.opt.drill == Drill::Wall => {
+                self.end_shot(Some(back));
+                if !self.done {
+                    self.start_shot();
+                }
+            },
+            None => (),
+        }
+    }
+}
+
+impl event::EventHandler for Practice {
+    fn key_up_event(&mut self, _ctx: &mut Context, keycode: event::KeyCode, _keymods: event::KeyMods) {
+        if keycode != event::KeyCode::Space {
+            return;
+        }
+        if self.player.calibrating() {
+            self.player.finish_calibration();
+        } else if self.done {
+            self.restart();
+        }
+    }
+
+    fn gamepad_button_down_event(&mut self, _ctx: &mut Context, btn: Button, id: GamepadId) {
+        self.pads.button(btn, true, id);
+    }
+
+    fn gamepad_button_up_event(&mut self, _ctx: &mut Context, btn: Button, id: GamepadId) {
+        self.pads.button(btn, false, id);
+        if btn != Button::Start && btn != Button::Select {
+            return;
+        }
+        if self.player.calibrating() {
+            self.player.finish_calibration();
+        } else if self.done {
+            self.restart();
+        }
+    }
+
+    fn gamepad_axis_event(&mut self, _ctx: &mut Context, axis: Axis, value: f32, id: GamepadId) {
+        self.pads.axis(axis, value, id);
+    }
+
+    fn resize_event(&mut self, ctx: &mut Context, width: f32, height: f32) {
+        self.view.refit(ctx, width, height).expect("Couldn't rescale the playfield");
+    }
+
+    fn update(&mut self, ctx: &mut Context) -> GameResult {
+        let dt = ggez::timer::delta(ctx).as_secs_f32();
+        if dt >= 0.1 {
+            return Ok(());
+        }
+        let on_field = [self.player.paddle()];
+        let devices = Devices{
+            pads: &self.pads,
+            view: &self.view,
+            remote: [None; 4],
+            remote_serve: [false; 4],
+            paddles: &on_field,
+            scores: [0; 4],
+        };
+        let balls: &[Ball] = match &self.ball {
+            Some(ball) => std::slice::from_ref(ball),
+            None => &[],
+        };
+        self.player.update(Some(ctx), dt, &devices, balls);
+        if self.player.calibrating() || self.done {
+            return Ok(());
+        }
+
+        if self.ball.is_none() {
+            self.wait -= dt;
+            if self.wait <= 0.0 {
+                self.fire(ctx);
+            }
+        }
+        self.play(ctx, dt);
+        Ok(())
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        self.view.clear(ctx)?;
+        if self.player.calibrating() {
+            message(ctx, "Turn the knob to its top stop, then its bottom stop.\nA knob set up before only needs the top stop.\nHit [space] or Start when done.")?;
+            return graphics::present(ctx);
+        }
+
+        // the far end: a solid wall, or the cannon's muzzle at its last shot
+        let far = match (self.opt.drill, &self.ball) {
+            (Drill::Wall, _) => graphics::Rect::new(SCREEN_WIDTH - 6.0, 0.0, 6.0, SCREEN_HEIGHT),
+            (_, Some(ball)) if self.shot.is_some() && ball.vel.x < 0.0 => {
+                let along = ball.pos.y.clamp(30.0, SCREEN_HEIGHT - 30.0);
+                graphics::Rect::new(SCREEN_WIDTH - 24.0, along - 10.0, 24.0, 20.0)
+            },
+            _ => graphics::Rect::new(SCREEN_WIDTH - 24.0, SCREEN_HEIGHT / 2.0 - 10.0, 24.0, 20.0),
+        };
+        let mesh = graphics::Mesh::new_rectangle(ctx, graphics::DrawMode::fill(), far, graphics::WHITE)?;
+        graphics::draw(ctx, &mesh, graphics::DrawParam::default())?;
+
+        self.player.draw(ctx)?;
+        if let Some(ball) = &self.ball {
+            ball.draw(ctx)?;
+        }
+
+        let drill = self.opt.drill.name();
+        let header = format!("{} drill   shot {} of {}\n{}",
+            drill, self.fired.min(self.opt.shots), self.opt.shots, self.session.describe());
+        graphics::draw(ctx, &graphics::Text::new(header), graphics::DrawParam::default().dest([SCREEN_WIDTH * 0.25, 20.0]))?;
+        if self.done {
+            let all = self.progress.drills.get(drill).copied().unwrap_or_default();
+            message(ctx, &format!("Session over.\n\nAll {} sessions of {}:\n{}\nbest {:.0}%\n\nHit [space] to go again.",
+                all.sessions, drill, all.describe(), all.best * 100.0))?;
+        }
+        graphics::present(ctx)
+    }
+}
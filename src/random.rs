@@ -0,0 +1,18 @@
+use std::cell::RefCell;
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+// Every random choice in the game comes from here, so a headless game
+// started from the same seed plays out the same way every time.
+thread_local! {
+    static RNG: RefCell<StdRng> = RefCell::new(StdRng::from_entropy());
+}
+
+pub fn seed(seed: u64) {
+    RNG.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(seed));
+}
+
+pub fn with<T>(f: impl FnOnce(&mut StdRng) -> T) -> T {
+    RNG.with(|rng| f(&mut rng.borrow_mut()))
+}
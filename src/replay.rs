@@ -0,0 +1,119 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+
+use crate::input::{PaddleInput, Frame, Command};
+use crate::flexcontrol::SerialRead;
+
+// One command per tick, one tick per line:
+//   H          hold
+//   M <dy>     move by dy pixels
+//   G <y>      go to y
+// followed by " S" on ticks where the player was asking to serve.
+fn format_command(command: Command, serve: bool) -> String {
+    let line = match command {
+        Command::Hold    => "H".to_string(),
+        Command::Move(d) => format!("M {}", d),
+        Command::Goto(y) => format!("G {}", y),
+    };
+    if serve {line + " S"} else {line}
+}
+
+fn parse_command(line: &str) -> (Command, bool) {
+    let (line, serve) = match line.trim_end().strip_suffix(" S") {
+        Some(line) => (line, true),
+        None => (line, false),
+    };
+    let command = match line.split_at(1.min(line.len())) {
+        ("M", value) => value.trim().parse::<f32>().map_or(Command::Hold, Command::Move),
+        ("G", value) => value.trim().parse::<f32>().map_or(Command::Hold, Command::Goto),
+        (_, _) => Command::Hold,
+    };
+    (command, serve)
+}
+
+// Wraps another controller and writes down everything it does.
+pub struct Recorder {
+    inner: Box<dyn PaddleInput>,
+    out: BufWriter<File>,
+    // this tick's command, written once we know whether it served
+    pending: Option<Command>,
+}
+
+impl Recorder {
+    pub fn new(inner: Box<dyn PaddleInput>, path: &str) -> Self {
+        let file = File::create(path).expect("Couldn't create recording");
+        Recorder{inner, out: BufWriter::new(file), pending: None}
+    }
+
+    fn write(&mut self, serve: bool) {
+        if let Some(command) = self.pending.take() {
+            writeln!(self.out, "{}", format_command(command, serve)).expect("Couldn't write recording");
+        }
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        self.write(false);
+    }
+}
+
+impl PaddleInput for Recorder {
+    fn poll(&mut self, frame: &Frame) -> Command {
+        self.write(false);
+        let command = self.inner.poll(frame);
+        self.pending = Some(command);
+        command
+    }
+
+    fn serve(&mut self, frame: &Frame) -> bool {
+        let serve = self.inner.serve(frame);
+        self.write(serve);
+        serve
+    }
+
+    fn calibrating(&self) -> bool {
+        self.inner.calibrating()
+    }
+
+    fn finish_calibration(&mut self) {
+        self.inner.finish_calibration();
+    }
+
+    fn new_match(&mut self) {
+        self.inner.new_match();
+    }
+
+    fn take_serial_read(&mut self) -> Option<SerialRead> {
+        self.inner.take_serial_read()
+    }
+}
+
+// Plays a recording back tick for tick, then holds.
+pub struct ReplayInput {
+    commands: std::vec::IntoIter<(Command, bool)>,
+    serve: bool,
+}
+
+impl ReplayInput {
+    pub fn new(path: &str) -> Self {
+        let file = File::open(path).expect("Couldn't open recording");
+        let commands: Vec<(Command, bool)> = BufReader::new(file).lines()
+            .map_while(Result::ok)
+            .map(|line| parse_command(&line))
+            .collect();
+        ReplayInput{commands: commands.into_iter(), serve: false}
+    }
+}
+
+impl PaddleInput for ReplayInput {
+    fn poll(&mut self, _frame: &Frame) -> Command {
+        let (command, serve) = self.commands.next().unwrap_or((Command::Hold, false));
+        self.serve = serve;
+        command
+    }
+
+    fn serve(&mut self, _frame: &Frame) -> bool {
+        self.serve
+    }
+}
@@ -0,0 +1,53 @@
+use std::str::FromStr;
+
+use crate::powerup::Kinds;
+
+// Who puts the ball back in play after a point.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Serve {
+    // the classic game: from the middle, in a random direction
+    #[default]
+    Centre,
+    // each player in turn
+    Alternate,
+    // whoever let the point in
+    Loser,
+    // whoever won the point
+    Winner,
+}
+
+impl FromStr for Serve {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "centre" | "center" => Ok(Serve::Centre),
+            "alternate" => Ok(Serve::Alternate),
+            "loser"     => Ok(Serve::Loser),
+            "winner"    => Ok(Serve::Winner),
+            _ => Err(format!("unknown serve rule {}", s)),
+        }
+    }
+}
+
+// Match variants that change how the game plays, as opposed to how it is
+// controlled or displayed.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Rules {
+    // moving paddles put spin on the ball, which curves in flight and
+    // kicks off the walls
+    pub spin: bool,
+    // multi-ball: another ball joins after this many consecutive hits on
+    // any ball in play, and/or every so many seconds
+    pub extra_ball_hits: Option<i32>,
+    pub extra_ball_every: Option<f32>,
+    // no more balls than this from multi-ball or power-ups
+    pub max_balls: usize,
+    // power-ups that can appear, none for a straight match, and how many
+    // seconds apart
+    pub powerups: Kinds,
+    pub powerup_every: f32,
+    pub serve: Serve,
+    // seconds a server can hold the ball before it goes by itself
+    pub serve_timeout: f32,
+}
@@ -0,0 +1,109 @@
+// How games and the match are won.
+#[derive(Clone, Copy, Debug)]
+pub struct MatchRules {
+    // points to win a game; with three or more players, goals let in
+    // before a player is out
+    pub points: i32,
+    // lead needed to win a two player game, 2 for deuce
+    pub win_by: i32,
+    // the match is the best of this many games
+    pub games: i32,
+    // players change ends after every game
+    pub switch_sides: bool,
+}
+
+// Keeps score across the games of a match. Players are counted in the
+// order MainState holds them.
+pub struct Match {
+    pub rules: MatchRules,
+    players: usize,
+    // games won, by player
+    games: Vec<i32>,
+    // winner and final points of each game played
+    history: Vec<(usize, Vec<i32>)>,
+}
+
+fn join(points: &[i32]) -> String {
+    points.iter().map(|p| p.to_string()).collect::<Vec<_>>().join("-")
+}
+
+impl Match {
+    pub fn new(rules: MatchRules, players: usize) -> Self {
+        Match{rules, players, games: vec![0; players], history: vec![]}
+    }
+
+    pub fn restart(&mut self) {
+        self.games = vec![0; self.players];
+        self.history.clear();
+    }
+
+    pub fn played(&self) -> usize {
+        self.history.len()
+    }
+
+    pub fn is_out(&self, conceded: i32) -> bool {
+        self.players > 2 && conceded >= self.rules.points
+    }
+
+    // Who has won the game in progress. Two players play to the points
+    // target with the required lead; more play until one is left.
+    pub fn game_winner(&self, points: &[i32], conceded: &[i32]) -> Option<usize> {
+        if self.players > 2 {
+            let mut standing = (0..self.players).filter(|&i| !self.is_out(conceded[i]));
+            return match (standing.next(), standing.next()) {
+                (Some(winner), None) => Some(winner),
+                _ => None,
+            };
+        }
+        (0..2).find(|&i| points[i] >= self.rules.points && points[i] - points[1 - i] >= self.rules.win_by)
+    }
+
+    // what the umpire calls once a two player game goes to deuce
+    pub fn call(&self, points: &[i32]) -> Option<String> {
+        let edge = self.rules.points - 1;
+        if self.players != 2 || self.rules.win_by < 2 || points[0] < edge || points[1] < edge {
+            return None;
+        }
+        match points[0] - points[1] {
+            0  => Some("Deuce".to_string()),
+            1  => Some("Advantage P1".to_string()),
+            -1 => Some("Advantage P2".to_string()),
+            _  => None,
+        }
+    }
+
+    pub fn finish_game(&mut self, winner: usize, points: Vec<i32>) {
+        self.games[winner] += 1;
+        self.history.push((winner, points));
+    }
+
+    pub fn games_won(&self, player: usize) -> i32 {
+        self.games[player]
+    }
+
+    // points a player scored over the games finished so far
+    pub fn points_won(&self, player: usize) -> i32 {
+        self.history.iter().map(|(_, points)| points[player]).sum()
+    }
+
+    // the match winner, once someone has won more than half the games
+    pub fn winner(&self) -> Option<usize> {
+        self.games.iter().position(|&games| games * 2 > self.rules.games)
+    }
+
+    pub fn summary(&self) -> String {
+        let headline = match (self.winner(), self.history.last()) {
+            (Some(winner), _) => format!("Match to P{}", winner + 1),
+            (None, Some((winner, _))) => format!("Game to P{}", winner + 1),
+            (None, None) => String::new(),
+        };
+        let mut lines = vec![headline, String::new()];
+        for (i, (_, points)) in self.history.iter().enumerate() {
+            lines.push(format!("Game {}:  {}", i + 1, join(points)));
+        }
+        if self.rules.games > 1 {
+            lines.push(format!("Games:  {}", join(&self.games)));
+        }
+        lines.join("\n")
+    }
+}
@@ -0,0 +1,177 @@
+use std::cell::Cell;
+use std::fs;
+use std::rc::Rc;
+use std::time::{Duration, Instant, SystemTime};
+
+use rhai::{CallFnOptions, Dynamic, Engine, Map, Scope, AST};
+
+use crate::Position;
+use crate::input::{self, Command, Frame, PaddleInput};
+
+// longest a bot may think each tick before it is cut off
+const BUDGET: Duration = Duration::from_millis(2);
+// seconds between looks at the script file for changes
+const RELOAD_EVERY: f32 = 0.5;
+
+fn point(p: Position) -> Map {
+    let mut map = Map::new();
+    map.insert("x".into(), Dynamic::from_float(p.x.into()));
+    map.insert("y".into(), Dynamic::from_float(p.y.into()));
+    map
+}
+
+// What a bot gets each tick, as a map:
+//   side                 "left", "right", "top" or "bottom"
+//   dt                   seconds since the last tick
+//   ball                 #{x, y, vx, vy} for the ball coming its way
+//   paddle               #{x, y, length, score} for its own paddle
+//   paddles              the same for every paddle, each with its side
+// Its `update` function hands back how far to move along the wall this
+// tick: positive is down for left and right, right for top and bottom.
+// Inside it `this` is a map the bot can keep its own notes in.
+fn game(frame: &Frame) -> Map {
+    let mut ball = point(frame.ball);
+    ball.insert("vx".into(), Dynamic::from_float(frame.ball_vel.x.into()));
+    ball.insert("vy".into(), Dynamic::from_float(frame.ball_vel.y.into()));
+
+    let mut paddles = vec![];
+    let mut me = Map::new();
+    for paddle in frame.paddles {
+        let mut map = point(paddle.rect.centre());
+        map.insert("side".into(), paddle.side.name().into());
+        map.insert("length".into(), Dynamic::from_float(paddle.length().into()));
+        map.insert("score".into(), (frame.scores[paddle.side as usize] as i64).into());
+        if paddle.side == frame.side {
+            me = map.clone();
+        }
+        paddles.push(Dynamic::from_map(map));
+    }
+
+    let mut game = Map::new();
+    game.insert("side".into(), frame.side.name().into());
+    game.insert("dt".into(), Dynamic::from_float(frame.dt.into()));
+    game.insert("ball".into(), Dynamic::from_map(ball));
+    game.insert("paddle".into(), Dynamic::from_map(me));
+    game.insert("paddles".into(), paddles.into());
+    game
+}
+
+// A paddle bot written in Rhai. The script can't touch files or the
+// network, gets BUDGET to decide each tick and is reloaded whenever it
+// changes on disk. A script that fails or runs over just holds still for
+// that tick; the error is shown once, not again until the script has had
+// a good tick or been reloaded.
+pub struct ScriptInput {
+    path: String,
+    engine: Engine,
+    ast: Option<AST>,
+    memory: Dynamic,
+    modified: Option<SystemTime>,
+    since_check: f32,
+    deadline: Rc<Cell<Instant>>,
+    // an error has been shown and not yet got over
+    failing: bool,
+}
+
+impl ScriptInput {
+    pub fn new(path: &str) -> Self {
+        let deadline = Rc::new(Cell::new(Instant::now()));
+        let mut engine = Engine::new();
+        engine.disable_symbol("eval");
+        // scripts can't pull in other files
+        engine.set_module_resolver(rhai::module_resolvers::DummyModuleResolver::new());
+        engine.set_max_operations(1_000_000);
+        engine.set_max_call_levels(32);
+        engine.set_max_string_size(4096);
+        engine.set_max_array_size(4096);
+        engine.set_max_map_size(4096);
+        let limit = deadline.clone();
+        engine.on_progress(move |ops| {
+            if ops % 256 == 0 && Instant::now() > limit.get() {
+                Some(Dynamic::UNIT)
+            } else {
+                None
+            }
+        });
+
+        let mut bot = ScriptInput{
+            path: path.to_string(),
+            engine,
+            ast: None,
+            memory: Dynamic::from_map(Map::new()),
+            modified: None,
+            since_check: 0.0,
+            deadline,
+            failing: false,
+        };
+        bot.reload();
+        if bot.ast.is_none() {
+            panic!("Couldn't load bot script {}", path);
+        }
+        bot
+    }
+
+    fn reload(&mut self) {
+        self.modified = fs::metadata(&self.path).and_then(|meta| meta.modified()).ok();
+        self.failing = false;
+        match self.engine.compile_file(self.path.clone().into()) {
+            Ok(ast) => self.ast = Some(ast),
+            Err(err) => self.report(&err),
+        }
+    }
+
+    fn report(&mut self, err: &dyn std::fmt::Display) {
+        if !self.failing {
+            eprintln!("{}: {}", self.path, err);
+            self.failing = true;
+        }
+    }
+
+    fn check_for_changes(&mut self, dt: f32) {
+        self.since_check += dt;
+        if self.since_check < RELOAD_EVERY {
+            return;
+        }
+        self.since_check = 0.0;
+        let modified = fs::metadata(&self.path).and_then(|meta| meta.modified()).ok();
+        if modified != self.modified {
+            self.reload();
+        }
+    }
+}
+
+impl PaddleInput for ScriptInput {
+    fn poll(&mut self, frame: &Frame) -> Command {
+        self.check_for_changes(frame.dt);
+        let ast = match &self.ast {
+            Some(ast) => ast,
+            None => return Command::Hold,
+        };
+        self.deadline.set(Instant::now() + BUDGET);
+        let options = CallFnOptions::new().eval_ast(false).bind_this_ptr(&mut self.memory);
+        let result = self.engine.call_fn_with_options::<Dynamic>(
+            options, &mut Scope::new(), ast, "update", (game(frame),),
+        );
+        let d = match result {
+            Ok(d) => d.as_float().map(|d| d as f32).or_else(|_| d.as_int().map(|d| d as f32)),
+            Err(err) => {
+                self.report(&err);
+                return Command::Hold;
+            },
+        };
+        match d {
+            Ok(d) => {
+                self.failing = false;
+                input::bot_move(d, frame.dt)
+            },
+            Err(kind) => {
+                self.report(&format!("update returned {} rather than a number", kind));
+                Command::Hold
+            },
+        }
+    }
+
+    fn serve(&mut self, _frame: &Frame) -> bool {
+        true
+    }
+}
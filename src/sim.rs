@@ -0,0 +1,163 @@
+use crate::ball::Ball;
+use crate::arena::Arena;
+use crate::collision::Side;
+use crate::handicap::Handicap;
+use crate::make_player;
+use crate::input::{Devices, Gamepads, InputConfig, ResponseCurve};
+use crate::physics::PhysicsConfig;
+use crate::player::{Player, PlayerType};
+use crate::rules::Rules;
+use crate::scoring::Match;
+use crate::view::Viewport;
+
+// simulated seconds per tick
+pub const TICK: f32 = 1.0 / 60.0;
+
+// a player for a headless game
+pub fn player(side: Side, me: &PlayerType, physics: &PhysicsConfig) -> Player {
+    // bots never read these, but every controller is built with them
+    let input = InputConfig{
+        sensitivity: 1.0,
+        acceleration: 0.0,
+        curve: ResponseCurve{deadzone: 0.0, exponent: 1.0},
+        calibrate: false,
+    };
+    make_player(side, me, input, &None, &Handicap::default(), physics)
+}
+
+// A game with no window or sound, for computer players and bots only. It
+// plays classic rules, one ball served from the centre, and runs as fast
+// as it is stepped.
+pub struct Sim {
+    players: Vec<Player>,
+    ball: Ball,
+    rules: Rules,
+    physics: PhysicsConfig,
+    arena: Arena,
+    pub scoring: Match,
+    pads: Gamepads,
+    view: Viewport,
+    // simulated seconds so far
+    pub time: f32,
+}
+
+impl Sim {
+    pub fn new(players: Vec<Player>, scoring: Match, physics: PhysicsConfig) -> Self {
+        let rules = Rules::default();
+        Sim{
+            players,
+            ball: Ball::silent(0, rules, physics),
+            rules,
+            physics,
+            arena: Arena::load("classic"),
+            scoring,
+            pads: Gamepads::default(),
+            view: Viewport::unscaled(),
+            time: 0.0,
+        }
+    }
+
+    pub fn players(&self) -> &[Player] {
+        &self.players
+    }
+
+    pub fn ball(&self) -> &Ball {
+        &self.ball
+    }
+
+    pub fn arena(&self) -> &Arena {
+        &self.arena
+    }
+
+    pub fn points(&self) -> Vec<i32> {
+        self.players.iter().map(|player| player.score).collect()
+    }
+
+    fn goals(&self) -> Vec<Side> {
+        self.players.iter()
+            .filter(|player| !self.scoring.is_out(player.conceded))
+            .map(Player::side)
+            .collect()
+    }
+
+    // One tick. Returns the side the ball went out by, if it did.
+    pub fn step(&mut self) -> Option<Side> {
+        self.time += TICK;
+        let on_field: Vec<_> = self.players.iter().map(Player::paddle).collect();
+        let mut scores = [0; 4];
+        for player in &self.players {
+            scores[player.side() as usize] = player.score;
+        }
+        let devices = Devices{
+            pads: &self.pads,
+            view: &self.view,
+            remote: [None; 4],
+            remote_serve: [false; 4],
+            paddles: &on_field,
+            scores,
+        };
+        let balls = std::slice::from_ref(&self.ball);
+        for player in &mut self.players {
+            player.update(None, TICK, &devices, balls);
+            player.anticipate(balls, TICK);
+        }
+
+        let goals = self.goals();
+        let paddles: Vec<_> = self.players.iter()
+            .filter(|player| goals.contains(&player.side()))
+            .map(Player::paddle)
+            .collect();
+        let last_hit = self.ball.last_hit();
+        let goal = self.ball.update(TICK, &paddles, &goals, &self.arena);
+        if let Some(side) = goal {
+            self.score(side, last_hit);
+        }
+        goal
+    }
+
+    // scores the same way as a game in a window
+    fn score(&mut self, side: Side, last_hit: Option<Side>) {
+        let two_player = self.players.len() == 2;
+        for player in &mut self.players {
+            if player.side() == side {
+                player.conceded += 1;
+            } else if two_player || last_hit == Some(player.side()) {
+                player.update_score(1);
+            }
+        }
+
+        let points = self.points();
+        let conceded: Vec<i32> = self.players.iter().map(|player| player.conceded).collect();
+        if let Some(winner) = self.scoring.game_winner(&points, &conceded) {
+            self.scoring.finish_game(winner, points);
+            for player in &mut self.players {
+                player.restart();
+                if self.scoring.rules.switch_sides {
+                    player.set_side(player.side().opposite());
+                }
+            }
+            self.ball = Ball::silent(0, self.rules, self.physics);
+        }
+    }
+
+    // Plays until the match is won or `max_time` simulated seconds have
+    // gone. A match that runs out of time goes to whoever is ahead, first
+    // on games and then on points in the game being played; level is a
+    // draw and gives none.
+    pub fn play(&mut self, max_time: f32) -> Option<usize> {
+        while self.time < max_time {
+            if let Some(winner) = self.scoring.winner() {
+                return Some(winner);
+            }
+            self.step();
+        }
+        if let Some(winner) = self.scoring.winner() {
+            return Some(winner);
+        }
+        let points = self.points();
+        let lead = |i: usize| (self.scoring.games_won(i), points[i]);
+        let best = (0..self.players.len()).max_by_key(|&i| lead(i))?;
+        let tied = (0..self.players.len()).filter(|&i| lead(i) == lead(best)).count() > 1;
+        if tied {None} else {Some(best)}
+    }
+}
@@ -0,0 +1,373 @@
+use std::f32::consts::{LN_10, PI};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::str::FromStr;
+
+use serde::Serialize;
+use structopt::StructOpt;
+
+use crate::{check_player, to_player_type};
+use crate::collision::Side;
+use crate::physics::PhysicsConfig;
+use crate::player::PlayerType;
+use crate::random;
+use crate::scoring::{Match, MatchRules};
+use crate::sim::{self, Sim};
+
+// starting rating and, for Glicko, starting and smallest deviation
+const START_RATING: f32 = 1500.0;
+const START_DEVIATION: f32 = 350.0;
+const MIN_DEVIATION: f32 = 30.0;
+// Elo rating change for a full upset
+const ELO_K: f32 = 32.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    RoundRobin,
+    Swiss,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "round-robin" => Ok(Format::RoundRobin),
+            "swiss"       => Ok(Format::Swiss),
+            _ => Err(format!("unknown format '{}', try round-robin or swiss", s)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Rating {
+    Elo,
+    Glicko,
+}
+
+impl FromStr for Rating {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "elo"    => Ok(Rating::Elo),
+            "glicko" => Ok(Rating::Glicko),
+            _ => Err(format!("unknown rating '{}', try elo or glicko", s)),
+        }
+    }
+}
+
+// netpong tournament [options] <players>...
+#[derive(StructOpt, Debug)]
+#[structopt(name = "tournament")]
+pub struct TournamentOpt {
+    /// Players: computer[:easy|normal|hard|perfect|adaptive], script:<file> or bot:<command>
+    #[structopt(required = true, min_values = 2, validator = check_player)]
+    players: Vec<String>,
+    /// round-robin or swiss
+    #[structopt(long, default_value = "round-robin")]
+    format: Format,
+    /// Rounds in a Swiss tournament
+    #[structopt(long, default_value = "5")]
+    rounds: usize,
+    /// Seed for the first match; each match after it uses the next one
+    #[structopt(long, default_value = "1")]
+    seed: u64,
+    /// elo or glicko
+    #[structopt(long, default_value = "elo")]
+    rating: Rating,
+    /// Points to win a game
+    #[structopt(long, default_value = "11")]
+    points: i32,
+    /// Lead needed to win a game
+    #[structopt(long, default_value = "2")]
+    win_by: i32,
+    /// Each match is the best of this many games
+    #[structopt(long, default_value = "3")]
+    games: i32,
+    /// Simulated seconds after which a match is decided on the score so far
+    #[structopt(long, default_value = "900")]
+    max_time: f32,
+    /// TOML file of physics parameters
+    #[structopt(long)]
+    physics: Option<String>,
+    /// Write every match result to this CSV file
+    #[structopt(long)]
+    csv: Option<String>,
+    /// Write the standings and every match result to this JSON file
+    #[structopt(long)]
+    json: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Standing {
+    name: String,
+    played: u32,
+    won: u32,
+    drawn: u32,
+    lost: u32,
+    points_for: i32,
+    points_against: i32,
+    // a win is worth 1, a draw half
+    score: f32,
+    rating: f32,
+    // Glicko only
+    deviation: Option<f32>,
+    #[serde(skip)]
+    opponents: Vec<usize>,
+}
+
+#[derive(Serialize)]
+struct MatchResult {
+    round: usize,
+    seed: u64,
+    left: String,
+    right: String,
+    left_games: i32,
+    right_games: i32,
+    left_points: i32,
+    right_points: i32,
+    // none for a draw
+    winner: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    standings: Vec<&'a Standing>,
+    matches: &'a [MatchResult],
+}
+
+struct Tournament {
+    opt: TournamentOpt,
+    types: Vec<PlayerType>,
+    physics: PhysicsConfig,
+    standings: Vec<Standing>,
+    matches: Vec<MatchResult>,
+    next_seed: u64,
+}
+
+fn expected(rating: f32, against: f32) -> f32 {
+    1.0 / (1.0 + 10f32.powf((against - rating) / 400.0))
+}
+
+// Glicko's weighting for how sure we are of a rating
+fn weight(deviation: f32) -> f32 {
+    let q = LN_10 / 400.0;
+    1.0 / (1.0 + 3.0 * q * q * deviation * deviation / (PI * PI)).sqrt()
+}
+
+impl Tournament {
+    fn new(opt: TournamentOpt) -> Self {
+        let types: Vec<PlayerType> = opt.players.iter().map(|name| match to_player_type(name) {
+            PlayerType::Human(_) | PlayerType::Network(_) => panic!("{} can't play in a tournament", name),
+            me => me,
+        }).collect();
+        let standings = opt.players.iter().map(|name| Standing{
+            name: name.clone(),
+            played: 0, won: 0, drawn: 0, lost: 0,
+            points_for: 0, points_against: 0,
+            score: 0.0,
+            rating: START_RATING,
+            deviation: if opt.rating == Rating::Glicko {Some(START_DEVIATION)} else {None},
+            opponents: vec![],
+        }).collect();
+        let physics = match &opt.physics {
+            Some(path) => PhysicsConfig::load(path),
+            None => PhysicsConfig::default(),
+        };
+        Tournament{next_seed: opt.seed, opt, types, physics, standings, matches: vec![]}
+    }
+
+    fn play(&mut self, round: usize, left: usize, right: usize) {
+        let seed = self.next_seed;
+        self.next_seed += 1;
+        random::seed(seed);
+
+        let players = vec![
+            sim::player(Side::Left, &self.types[left], &self.physics),
+            sim::player(Side::Right, &self.types[right], &self.physics),
+        ];
+        let rules = MatchRules{
+            points: self.opt.points,
+            win_by: self.opt.win_by,
+            games: self.opt.games,
+            switch_sides: false,
+        };
+        let mut sim = Sim::new(players, Match::new(rules, 2), self.physics);
+        let winner = sim.play(self.opt.max_time);
+
+        let current = sim.points();
+        let games = [sim.scoring.games_won(0), sim.scoring.games_won(1)];
+        let points = [sim.scoring.points_won(0) + current[0], sim.scoring.points_won(1) + current[1]];
+        let entrants = [left, right];
+        let scores = match winner {
+            Some(0) => [1.0, 0.0],
+            Some(_) => [0.0, 1.0],
+            None    => [0.5, 0.5],
+        };
+        self.rate(entrants, scores);
+        for i in 0..2 {
+            let standing = &mut self.standings[entrants[i]];
+            standing.played += 1;
+            standing.points_for += points[i];
+            standing.points_against += points[1 - i];
+            standing.score += scores[i];
+            standing.opponents.push(entrants[1 - i]);
+            match winner {
+                Some(w) if w == i => standing.won += 1,
+                Some(_) => standing.lost += 1,
+                None => standing.drawn += 1,
+            }
+        }
+        self.matches.push(MatchResult{
+            round,
+            seed,
+            left: self.opt.players[left].clone(),
+            right: self.opt.players[right].clone(),
+            left_games: games[0],
+            right_games: games[1],
+            left_points: points[0],
+            right_points: points[1],
+            winner: winner.map(|w| self.opt.players[entrants[w]].clone()),
+        });
+        println!("round {}: {} {}-{} {}", round, self.opt.players[left], games[0], games[1], self.opt.players[right]);
+    }
+
+    // update both players' ratings from one match, `scores` being 1, 0.5 or 0
+    fn rate(&mut self, entrants: [usize; 2], scores: [f32; 2]) {
+        let before: Vec<(f32, Option<f32>)> = entrants.iter()
+            .map(|&i| (self.standings[i].rating, self.standings[i].deviation))
+            .collect();
+        for i in 0..2 {
+            let (rating, deviation) = before[i];
+            let (against, their_deviation) = before[1 - i];
+            let standing = &mut self.standings[entrants[i]];
+            match (deviation, their_deviation) {
+                (Some(deviation), Some(their_deviation)) => {
+                    let q = LN_10 / 400.0;
+                    let g = weight(their_deviation);
+                    let e = 1.0 / (1.0 + 10f32.powf(-g * (rating - against) / 400.0));
+                    let d2 = 1.0 / (q * q * g * g * e * (1.0 - e));
+                    let precision = 1.0 / (deviation * deviation) + 1.0 / d2;
+                    standing.rating = rating + q / precision * g * (scores[i] - e);
+                    standing.deviation = Some((1.0 / precision).sqrt().max(MIN_DEVIATION));
+                },
+                _ => standing.rating = rating + ELO_K * (scores[i] - expected(rating, against)),
+            }
+        }
+    }
+
+    // Everyone meets everyone once, in rounds where nobody plays twice:
+    // the first player stays put while the rest rotate around a circle.
+    // With an odd number, whoever is paired with the empty seat sits out.
+    fn round_robin(&mut self) {
+        let n = self.types.len();
+        let seats = n + n % 2;
+        let mut circle: Vec<usize> = (0..seats).collect();
+        for round in 1..seats {
+            for i in 0..seats / 2 {
+                let (a, b) = (circle[i].min(circle[seats - 1 - i]), circle[i].max(circle[seats - 1 - i]));
+                if b == n {
+                    continue;
+                }
+                // take turns on the left
+                if (a + b) % 2 == 0 {self.play(round, a, b)} else {self.play(round, b, a)}
+            }
+            circle[1..].rotate_right(1);
+        }
+    }
+
+    // Each round pairs players on the same score who haven't met yet,
+    // best first. With an odd number the lowest placed player without a
+    // bye sits out and is given a win, though not a rating change.
+    fn swiss(&mut self) {
+        let mut byes = vec![];
+        for round in 1..=self.opt.rounds {
+            let mut order = self.ranking();
+            if order.len() % 2 == 1 {
+                let bye = *order.iter().rev().find(|i| !byes.contains(*i)).unwrap_or(&order[order.len() - 1]);
+                byes.push(bye);
+                order.retain(|&i| i != bye);
+                let standing = &mut self.standings[bye];
+                standing.played += 1;
+                standing.won += 1;
+                standing.score += 1.0;
+                println!("round {}: {} has a bye", round, self.opt.players[bye]);
+            }
+            while let Some(first) = order.first().copied() {
+                order.remove(0);
+                let fresh = order.iter().position(|i| !self.standings[first].opponents.contains(i)).unwrap_or(0);
+                let second = order.remove(fresh);
+                self.play(round, first, second);
+            }
+        }
+    }
+
+    // player indices, best first
+    fn ranking(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.standings.len()).collect();
+        order.sort_by(|&a, &b| {
+            let (a, b) = (&self.standings[a], &self.standings[b]);
+            b.score.partial_cmp(&a.score).unwrap()
+                .then(b.rating.partial_cmp(&a.rating).unwrap())
+                .then((b.points_for - b.points_against).cmp(&(a.points_for - a.points_against)))
+        });
+        order
+    }
+
+    fn print_standings(&self) {
+        let width = self.opt.players.iter().map(String::len).max().unwrap_or(0).max(6);
+        println!();
+        println!("{:>4}  {:<width$}  {:>3} {:>3} {:>3} {:>3}  {:>6} {:>6}  {:>5}  {:>7}",
+            "", "player", "P", "W", "D", "L", "for", "agst", "score", "rating", width = width);
+        for (rank, &i) in self.ranking().iter().enumerate() {
+            let s = &self.standings[i];
+            let rating = match s.deviation {
+                Some(deviation) => format!("{:.0} ±{:.0}", s.rating, deviation),
+                None => format!("{:.0}", s.rating),
+            };
+            println!("{:>4}  {:<width$}  {:>3} {:>3} {:>3} {:>3}  {:>6} {:>6}  {:>5.1}  {:>7}",
+                rank + 1, s.name, s.played, s.won, s.drawn, s.lost,
+                s.points_for, s.points_against, s.score, rating, width = width);
+        }
+    }
+
+    fn write_csv(&self, path: &str) {
+        let file = File::create(path).expect("Couldn't create CSV file");
+        let mut out = BufWriter::new(file);
+        let quote = |s: &str| format!("\"{}\"", s.replace('"', "\"\""));
+        writeln!(out, "round,seed,left,right,left_games,right_games,left_points,right_points,winner")
+            .expect("Couldn't write CSV file");
+        for m in &self.matches {
+            writeln!(out, "{},{},{},{},{},{},{},{},{}",
+                m.round, m.seed, quote(&m.left), quote(&m.right),
+                m.left_games, m.right_games, m.left_points, m.right_points,
+                m.winner.as_deref().map(quote).unwrap_or_default(),
+            ).expect("Couldn't write CSV file");
+        }
+    }
+
+    fn write_json(&self, path: &str) {
+        let report = Report{
+            standings: self.ranking().iter().map(|&i| &self.standings[i]).collect(),
+            matches: &self.matches,
+        };
+        let file = File::create(path).expect("Couldn't create JSON file");
+        serde_json::to_writer_pretty(BufWriter::new(file), &report).expect("Couldn't write JSON file");
+    }
+}
+
+pub fn run(opt: TournamentOpt) {
+    let mut tournament = Tournament::new(opt);
+    match tournament.opt.format {
+        Format::RoundRobin => tournament.round_robin(),
+        Format::Swiss      => tournament.swiss(),
+    }
+    tournament.print_standings();
+    if let Some(path) = &tournament.opt.csv {
+        tournament.write_csv(path);
+    }
+    if let Some(path) = &tournament.opt.json {
+        tournament.write_json(path);
+    }
+}
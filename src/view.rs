@@ -0,0 +1,63 @@
+use ggez::{Context, GameResult, graphics};
+
+use crate::{SCREEN_WIDTH, SCREEN_HEIGHT, Position};
+
+// colour of the bars either side of the playfield
+const LETTERBOX: graphics::Color = graphics::Color{r: 0.08, g: 0.08, b: 0.08, a: 1.0};
+
+// Maps the logical SCREEN_WIDTH x SCREEN_HEIGHT playfield into whatever size
+// the window is, keeping its aspect ratio and centring it. Physics and the
+// network only ever see logical units.
+#[derive(Clone, Copy, Debug)]
+pub struct Viewport {
+    scale: f32,
+    left:  f32,
+    top:   f32,
+}
+
+impl Viewport {
+    pub fn fit(ctx: &mut Context, width: f32, height: f32) -> GameResult<Viewport> {
+        let scale = (width / SCREEN_WIDTH).min(height / SCREEN_HEIGHT);
+        let view = Viewport{
+            scale,
+            left: -(width / scale - SCREEN_WIDTH) / 2.0,
+            top:  -(height / scale - SCREEN_HEIGHT) / 2.0,
+        };
+        graphics::set_screen_coordinates(
+            ctx,
+            graphics::Rect::new(view.left, view.top, width / scale, height / scale),
+        )?;
+        Ok(view)
+    }
+
+    // after the window is resized; a minimised window can report 0x0, and
+    // then the old fit is kept until it comes back
+    pub fn refit(&mut self, ctx: &mut Context, width: f32, height: f32) -> GameResult {
+        if width <= 0.0 || height <= 0.0 {
+            return Ok(());
+        }
+        *self = Viewport::fit(ctx, width, height)?;
+        Ok(())
+    }
+
+    // one window pixel to a playfield unit, for games with no window
+    pub fn unscaled() -> Viewport {
+        Viewport{scale: 1.0, left: 0.0, top: 0.0}
+    }
+
+    // window pixels, as reported for the mouse, to playfield units
+    pub fn to_logical(self, p: Position) -> Position {
+        Position{x: self.left + p.x / self.scale, y: self.top + p.y / self.scale}
+    }
+
+    pub fn clear(&self, ctx: &mut Context) -> GameResult {
+        graphics::clear(ctx, LETTERBOX);
+        let field = graphics::Mesh::new_rectangle(
+            ctx,
+            graphics::DrawMode::fill(),
+            graphics::Rect::new(0.0, 0.0, SCREEN_WIDTH, SCREEN_HEIGHT),
+            graphics::BLACK,
+        )?;
+        graphics::draw(ctx, &field, graphics::DrawParam::default())
+    }
+}